@@ -15,15 +15,18 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+use std::future::Future;
+
 use ripple_sdk::{
     framework::{
         bootstrap::{Bootstep, Bootstrap},
         RippleResponse,
     },
-    log::error,
+    log::{error, info},
+    tokio,
 };
 
-use crate::state::bootstrap_state::BootstrapState;
+use crate::state::{bootstrap_state::BootstrapState, extn_state::ExtnState};
 
 use super::{
     extn::{
@@ -33,7 +36,9 @@ use super::{
     },
     setup_extn_client_step::SetupExtnClientStep,
     start_app_manager_step::StartAppManagerStep,
-    start_communication_broker::{StartCommunicationBroker, StartOtherBrokers},
+    start_communication_broker::{
+        StartCommunicationBroker, StartOtherBrokers, WaitForRequiredEndpoints,
+    },
     start_fbgateway_step::FireboltGatewayStep,
     start_ws_step::StartWsStep,
 };
@@ -54,10 +59,11 @@ use super::{
 /// 5. [StartExtnChannelsStep] - Starts the Device channel extension
 /// 6. [StartAppManagerStep] - Starts the App Manager and other supporting services
 /// 7. [StartOtherBrokers] - Start Other brokers if they are setup in endpoints for rules
-/// 8. [LoadDistributorValuesStep] - Loads the values from distributor like Session
-/// 9. [CheckLauncherStep] - Checks the presence of launcher extension and starts default app
-/// 10. [StartWsStep] - Starts the Websocket to accept external and internal connections
-/// 11. [FireboltGatewayStep] - Starts the firebolt gateway and blocks the thread to keep it alive till interruption.
+/// 8. [WaitForRequiredEndpoints] - Waits for the endpoints marked as required to connect
+/// 9. [LoadDistributorValuesStep] - Loads the values from distributor like Session
+/// 10. [CheckLauncherStep] - Checks the presence of launcher extension and starts default app
+/// 11. [StartWsStep] - Starts the Websocket to accept external and internal connections
+/// 12. [FireboltGatewayStep] - Starts the firebolt gateway and blocks the thread to keep it alive till interruption.
 
 ///
 pub async fn boot(state: BootstrapState) -> RippleResponse {
@@ -69,6 +75,7 @@ pub async fn boot(state: BootstrapState) -> RippleResponse {
     execute_step(StartExtnChannelsStep, &bootstrap).await?;
     execute_step(StartAppManagerStep, &bootstrap).await?;
     execute_step(StartOtherBrokers, &bootstrap).await?;
+    execute_step(WaitForRequiredEndpoints, &bootstrap).await?;
     execute_step(LoadDistributorValuesStep, &bootstrap).await?;
     execute_step(CheckLauncherStep, &bootstrap).await?;
     execute_step(StartWsStep, &bootstrap).await?;
@@ -76,6 +83,49 @@ pub async fn boot(state: BootstrapState) -> RippleResponse {
     Ok(())
 }
 
+/// Boots Ripple and keeps running until bootstrap ends or `shutdown` completes. On shutdown the
+/// started extension channels are stopped before returning. Callers embedding Ripple which handle
+/// signals on their own can pass [std::future::pending] to opt out.
+pub async fn boot_until_shutdown<F>(state: BootstrapState, shutdown: F) -> RippleResponse
+where
+    F: Future<Output = ()>,
+{
+    let extn_state = state.extn_state.clone();
+    run_until_shutdown(boot(state), shutdown, &extn_state).await
+}
+
+async fn run_until_shutdown<R, F>(run: R, shutdown: F, extn_state: &ExtnState) -> RippleResponse
+where
+    R: Future<Output = RippleResponse>,
+    F: Future<Output = ()>,
+{
+    tokio::select! {
+        result = run => result,
+        _ = shutdown => {
+            info!("Shutdown requested, stopping extensions");
+            extn_state.stop_all_channels().await;
+            Ok(())
+        }
+    }
+}
+
+/// Completes when the process receives SIGTERM or SIGINT.
+pub async fn shutdown_signal() {
+    let mut sigterm = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        Ok(sigterm) => sigterm,
+        Err(e) => {
+            error!("Unable to listen for SIGTERM {:?}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = sigterm.recv() => info!("Received SIGTERM"),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+    }
+}
+
 async fn execute_step<T: Bootstep<BootstrapState>>(
     step: T,
     state: &Bootstrap<BootstrapState>,
@@ -88,3 +138,30 @@ async fn execute_step<T: Bootstep<BootstrapState>>(
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::bootstrap_state::ChannelsState;
+    use ripple_sdk::{api::manifest::extn_manifest::ExtnManifest, tokio::sync::oneshot};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_shutdown_completes_on_signal() {
+        let extn_state = ExtnState::new(ChannelsState::new(), ExtnManifest::default());
+        let (signal_tx, signal_rx) = oneshot::channel::<()>();
+        let shutdown = async move {
+            let _ = signal_rx.await;
+        };
+        let running = tokio::spawn(async move {
+            run_until_shutdown(std::future::pending(), shutdown, &extn_state).await
+        });
+
+        signal_tx.send(()).unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(1), running)
+            .await
+            .expect("shutdown did not complete")
+            .unwrap();
+        assert!(result.is_ok());
+    }
+}
@@ -81,6 +81,7 @@ impl Bootstep<BootstrapState> for LoadExtensionMetadataStep {
                 match r {
                     Some(loaded_extn) => {
                         info!("Adding {}", loaded_extn.metadata.symbols.len());
+                        loaded_extn.validate_symbols()?;
                         loaded_extns.push(loaded_extn);
                     }
                     None => warn!(
@@ -148,3 +148,73 @@ impl Bootstep<BootstrapState> for LoadExtensionsStep {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::state::{
+        extn_state::{ExtnState, LoadedLibrary},
+        platform_state::PlatformState,
+    };
+    use ripple_sdk::{
+        api::manifest::extn_manifest::{ExtnManifest, ExtnManifestEntry, ExtnSymbol},
+        extn::ffi::ffi_library::{ExtnMetadata, ExtnSymbolMetadata},
+        framework::ripple_contract::{ContractFulfiller, RippleContract},
+        libloading::{os::unix, Library},
+        semver::Version,
+        tokio,
+    };
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    /// Library declaring a device channel without exporting a channel builder. The test
+    /// binary itself is used as the library so building the channel fails.
+    fn unbuildable_device_channel() -> LoadedLibrary {
+        let id = "ripple:channel:device:thunder";
+        let library: Library = unix::Library::this().into();
+        let metadata = ExtnMetadata {
+            name: "thunder".to_owned(),
+            symbols: vec![ExtnSymbolMetadata::get(
+                ExtnId::try_from(id.to_owned()).unwrap(),
+                ContractFulfiller::new(vec![RippleContract::DeviceInfo]),
+                Version::new(1, 1, 0),
+            )],
+        };
+        let entry = ExtnManifestEntry {
+            path: "libthunder".to_owned(),
+            symbols: vec![ExtnSymbol {
+                id: id.to_owned(),
+                uses: Vec::new(),
+                fulfills: vec!["device_info".to_owned()],
+                config: None,
+                required: false,
+            }],
+            resolution: None,
+        };
+        LoadedLibrary::new(library, Box::new(metadata), entry)
+    }
+
+    #[tokio::test]
+    async fn test_device_channel_build_failure_fails_bootstrap() {
+        let channels_state = ChannelsState::new();
+        let extn_state = ExtnState::new(channels_state.clone(), ExtnManifest::default());
+        extn_state
+            .loaded_libraries
+            .write()
+            .unwrap()
+            .push(unbuildable_device_channel());
+        let state = BootstrapState {
+            start_time: Instant::now(),
+            platform_state: PlatformState::mock(),
+            channels_state,
+            extn_state: extn_state.clone(),
+        };
+
+        assert!(matches!(
+            LoadExtensionsStep.setup(state).await,
+            Err(RippleError::BootstrapError)
+        ));
+        assert!(extn_state.device_channels.read().unwrap().is_empty());
+    }
+}
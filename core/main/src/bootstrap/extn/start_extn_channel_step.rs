@@ -15,17 +15,20 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+use std::time::Duration;
+
 use ripple_sdk::{
-    api::status_update::ExtnStatus,
     async_trait::async_trait,
     framework::{bootstrap::Bootstep, RippleResponse},
-    log::{error, warn},
-    tokio::sync::mpsc,
+    log::error,
     utils::error::RippleError,
 };
 
 use crate::state::{bootstrap_state::BootstrapState, extn_state::PreLoadedExtnChannel};
 
+/// Time given to each extension to report `ExtnStatus::Ready` before bootstrap fails
+const EXTN_READY_TIMEOUT_SECS: u64 = 120;
+
 fn start_preloaded_channel(
     state: &BootstrapState,
     channel: PreLoadedExtnChannel,
@@ -42,7 +45,7 @@ fn start_preloaded_channel(
 
 /// Bootstep which starts the All Extns channels intitiating including the device interface connection channel.
 /// This step calls the start method on the all the Channels and waits for a successful
-/// [ExtnStatus] before proceeding to the next boot step.
+/// `ExtnStatus` before proceeding to the next boot step.
 pub struct StartExtnChannelsStep;
 
 #[async_trait]
@@ -76,26 +79,10 @@ impl Bootstep<BootstrapState> for StartExtnChannelsStep {
             }
         }
         for extn_id in extn_ids {
-            let (tx, mut tr) = mpsc::channel(1);
-            if !state
+            state
                 .extn_state
-                .add_extn_status_listener(extn_id.clone(), tx)
-            {
-                while let Some(v) = tr.recv().await {
-                    match v {
-                        ExtnStatus::Ready => break,
-                        // When Extension is in this state means it has minimal success criteria for continuing
-                        // yet not fully ready due to some errors.
-                        // Expectation of the system here progressive to wait for an eventual success
-                        // without exiting with the error
-                        ExtnStatus::Interrupted => warn!("{} extension is interrupted state. Bootstrap currently paused until extension becomes ready.", extn_id.to_string()),
-                        ExtnStatus::Error => {
-                            error!("{} extension failed to load. Ripple needs to be restarted.",extn_id.to_string());
-                            return Err(RippleError::BootstrapError);
-                        }
-                    }
-                }
-            }
+                .wait_for_ready(extn_id, Duration::from_secs(EXTN_READY_TIMEOUT_SECS))
+                .await?;
         }
 
         Ok(())
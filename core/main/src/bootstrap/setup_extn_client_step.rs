@@ -52,7 +52,10 @@ impl Bootstep<BootstrapState> for SetupExtnClientStep {
         client.add_request_processor(ConfigRequestProcessor::new(state.platform_state.clone()));
         client.add_request_processor(PinProcessor::new(state.platform_state.clone()));
         client.add_request_processor(KeyboardProcessor::new(state.platform_state.clone()));
-        client.add_event_processor(ExtnStatusProcessor::new(state.extn_state.clone()));
+        client.add_event_processor(ExtnStatusProcessor::new(
+            state.extn_state.clone(),
+            state.platform_state.clone(),
+        ));
         client.add_event_processor(AppEventsProcessor::new(state.platform_state.clone()));
         client.add_request_processor(StorageManagerProcessor::new(state.platform_state.clone()));
         client.add_request_processor(StoreUserGrantsProcessor::new(state.platform_state.clone()));
@@ -15,6 +15,8 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+use std::time::Duration;
+
 use ripple_sdk::{
     async_trait::async_trait, framework::bootstrap::Bootstep, utils::error::RippleError,
 };
@@ -72,3 +74,24 @@ impl Bootstep<BootstrapState> for StartOtherBrokers {
         Ok(())
     }
 }
+
+pub struct WaitForRequiredEndpoints;
+
+#[async_trait]
+impl Bootstep<BootstrapState> for WaitForRequiredEndpoints {
+    fn get_name(&self) -> String {
+        "WaitForRequiredEndpoints".into()
+    }
+
+    async fn setup(&self, state: BootstrapState) -> Result<(), RippleError> {
+        let timeout = state
+            .platform_state
+            .get_device_manifest()
+            .get_required_endpoints_timeout_ms();
+        state
+            .platform_state
+            .endpoint_state
+            .wait_for_required_endpoints(Duration::from_millis(timeout))
+            .await
+    }
+}
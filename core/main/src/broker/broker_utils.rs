@@ -17,34 +17,81 @@
 
 use std::time::Duration;
 
+use super::rules_engine::RuleEndpoint;
 use crate::utils::rpc_utils::extract_tcp_port;
 use futures::stream::{SplitSink, SplitStream};
 use futures_util::StreamExt;
 use ripple_sdk::{
     log::{error, info},
     tokio::{self, net::TcpStream},
+    utils::error::RippleError,
+};
+use tokio_tungstenite::{
+    client_async,
+    tungstenite::{
+        client::IntoClientRequest,
+        handshake::client::Request,
+        http::{HeaderName, HeaderValue},
+        Message,
+    },
+    WebSocketStream,
 };
-use tokio_tungstenite::{client_async, tungstenite::Message, WebSocketStream};
 
 pub struct BrokerUtils;
 
 impl BrokerUtils {
+    /// Connects to the given websocket endpoint, retrying until the handshake succeeds.
+    /// Returns an error without retrying if the endpoint url cannot be parsed.
     pub async fn get_ws_broker(
-        endpoint: &str,
+        rule_endpoint: &RuleEndpoint,
         alias: Option<String>,
-    ) -> (
-        SplitSink<WebSocketStream<TcpStream>, Message>,
-        SplitStream<WebSocketStream<TcpStream>>,
-    ) {
+    ) -> Result<
+        (
+            SplitSink<WebSocketStream<TcpStream>, Message>,
+            SplitStream<WebSocketStream<TcpStream>>,
+        ),
+        RippleError,
+    > {
+        let endpoint = &rule_endpoint.get_url();
         info!("Broker Endpoint url {}", endpoint);
         let url_path = if let Some(a) = alias {
             format!("{}{}", endpoint, a)
         } else {
             endpoint.to_owned()
         };
-        let url = url::Url::parse(&url_path).unwrap();
+        let mut url = match url::Url::parse(&url_path) {
+            Ok(url) => url,
+            Err(e) => {
+                error!("Broker url {} is invalid {:?}", url_path, e);
+                return Err(RippleError::BrokerError(format!(
+                    "invalid url {}: {}",
+                    url_path, e
+                )));
+            }
+        };
+        let host = match url.host_str() {
+            Some(host) => host.to_owned(),
+            None => {
+                error!("Broker url {} has no host", url_path);
+                return Err(RippleError::BrokerError(format!(
+                    "missing host in url {}",
+                    url_path
+                )));
+            }
+        };
         let port = extract_tcp_port(endpoint);
-        info!("Url host str {}", url.host_str().unwrap());
+        info!("Url host str {}", host);
+        if !rule_endpoint.query_params.is_empty() {
+            url.query_pairs_mut()
+                .extend_pairs(rule_endpoint.query_params.iter());
+        }
+        let url_path = if rule_endpoint.query_params.is_empty() {
+            url_path
+        } else {
+            url.to_string()
+        };
+        // Validate the handshake request once, it is rebuilt for every connection attempt
+        Self::get_ws_request(&url_path, rule_endpoint)?;
         let mut index = 0;
 
         loop {
@@ -52,8 +99,14 @@ impl BrokerUtils {
             if let Ok(v) = TcpStream::connect(&port).await {
                 // Setup handshake for websocket with the tcp port
                 // Some WS servers lock on to the Port but not setup handshake till they are fully setup
-                if let Ok((stream, _)) = client_async(url_path.clone(), v).await {
-                    break stream.split();
+                let request = Self::get_ws_request(&url_path, rule_endpoint)?;
+                match client_async(request, v).await {
+                    Ok((stream, _)) => break Ok(stream.split()),
+                    Err(e) => {
+                        if (index % 10).eq(&0) {
+                            error!("Broker handshake with {} failed {:?}", url_path, e);
+                        }
+                    }
                 }
             }
             if (index % 10).eq(&0) {
@@ -66,4 +119,111 @@ impl BrokerUtils {
             tokio::time::sleep(Duration::from_secs(1)).await;
         }
     }
+
+    /// Builds the websocket handshake request with the headers configured for the endpoint
+    fn get_ws_request(
+        url_path: &str,
+        rule_endpoint: &RuleEndpoint,
+    ) -> Result<Request, RippleError> {
+        let mut request = url_path
+            .into_client_request()
+            .map_err(|e| RippleError::BrokerError(e.to_string()))?;
+        for (name, value) in rule_endpoint.headers.iter() {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| RippleError::BrokerError(format!("header {}: {}", name, e)))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|e| RippleError::BrokerError(format!("header {}: {}", name, e)))?;
+            request.headers_mut().insert(header_name, header_value);
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::broker::rules_engine::RuleEndpointProtocol;
+    use ripple_sdk::tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::{
+        handshake::server::{ErrorResponse, Response},
+        http::StatusCode,
+    };
+
+    fn get_endpoint(url: &str) -> RuleEndpoint {
+        RuleEndpoint {
+            protocol: RuleEndpointProtocol::Websocket,
+            url: url.to_owned(),
+            jsonrpc: true,
+            http_jsonrpc: false,
+            timeout_ms: None,
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            channel_capacity: None,
+            required: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_ws_broker_invalid_url() {
+        let result = BrokerUtils::get_ws_broker(&get_endpoint("not a url"), None).await;
+        assert!(matches!(result, Err(RippleError::BrokerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_ws_broker_missing_host() {
+        let result = BrokerUtils::get_ws_broker(&get_endpoint("unix:/tmp/socket"), None).await;
+        assert!(matches!(result, Err(RippleError::BrokerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_ws_broker_invalid_header() {
+        let mut endpoint = get_endpoint("ws://127.0.0.1:9998/jsonrpc");
+        endpoint
+            .headers
+            .insert("Bad Header".to_owned(), "value".to_owned());
+        let result = BrokerUtils::get_ws_broker(&endpoint, None).await;
+        assert!(matches!(result, Err(RippleError::BrokerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_ws_broker_headers_and_query_params() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Server side validation of the handshake similar to the mock device server
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let callback = |request: &Request, response: Response| {
+                let authorized = request
+                    .headers()
+                    .get("Authorization")
+                    .map_or(false, |v| v == "Bearer token");
+                let has_query = request.uri().query() == Some("appId=test");
+                if authorized && has_query {
+                    Ok(response)
+                } else {
+                    let mut error = ErrorResponse::new(None);
+                    *error.status_mut() = StatusCode::BAD_REQUEST;
+                    Err(error)
+                }
+            };
+            let _ = tokio_tungstenite::accept_hdr_async(stream, callback).await;
+        });
+
+        let mut endpoint = get_endpoint(&format!("ws://127.0.0.1:{}/jsonrpc", port));
+        endpoint
+            .headers
+            .insert("Authorization".to_owned(), "Bearer token".to_owned());
+        endpoint
+            .query_params
+            .insert("appId".to_owned(), "test".to_owned());
+        let result = tokio::time::timeout(
+            Duration::from_secs(2),
+            BrokerUtils::get_ws_broker(&endpoint, None),
+        )
+        .await
+        .unwrap();
+        assert!(result.is_ok());
+    }
 }
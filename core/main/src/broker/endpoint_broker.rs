@@ -17,7 +17,13 @@
 
 use ripple_sdk::{
     api::{
-        firebolt::fb_capabilities::JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+        firebolt::{
+            fb_capabilities::{
+                JSON_RPC_SERVER_ERROR_TIMED_OUT, JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+            },
+            fb_metrics::{Tag, Timer},
+            fb_telemetry::OperationalMetricRequest,
+        },
         gateway::rpc_gateway_api::{
             ApiMessage, ApiProtocol, ApiStats, CallContext, JsonRpcApiResponse, RpcRequest,
         },
@@ -25,21 +31,22 @@ use ripple_sdk::{
     },
     extn::extn_client_message::{ExtnEvent, ExtnMessage},
     framework::RippleResponse,
-    log::{error, trace},
+    log::{debug, error, info, trace, warn},
     tokio::{
         self,
-        sync::mpsc::{self, Receiver, Sender},
+        sync::mpsc::{self, error::TrySendError, Receiver, Sender},
     },
     utils::error::RippleError,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, RwLock,
     },
+    time::{Duration, Instant},
 };
 
 use crate::{
@@ -63,6 +70,9 @@ use super::{
 #[derive(Clone, Debug)]
 pub struct BrokerSender {
     pub sender: Sender<BrokerRequest>,
+    /// Reject requests while the channel is full rather than waiting for the broker
+    reject_when_full: bool,
+    overflowed: Arc<AtomicBool>,
 }
 
 #[derive(Clone, Debug)]
@@ -96,6 +106,7 @@ pub struct BrokerConnectRequest {
     pub sub_map: BrokerSubMap,
     pub session: Option<AccountSession>,
     pub reconnector: Sender<BrokerConnectRequest>,
+    pub status_callback: Option<BrokerStatusCallback>,
 }
 
 impl BrokerConnectRequest {
@@ -110,6 +121,7 @@ impl BrokerConnectRequest {
             sub_map: HashMap::new(),
             session: None,
             reconnector,
+            status_callback: None,
         }
     }
 
@@ -125,6 +137,7 @@ impl BrokerConnectRequest {
             sub_map: HashMap::new(),
             session,
             reconnector,
+            status_callback: None,
         }
     }
 }
@@ -156,25 +169,90 @@ pub struct BrokerCallback {
     pub sender: Sender<BrokerOutput>,
 }
 
+/// Connection state of a broker with its endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrokerConnectionState {
+    Connected,
+    Disconnected,
+    Reconnecting,
+}
+
+#[derive(Debug, Clone)]
+pub struct BrokerStatus {
+    pub key: String,
+    pub state: BrokerConnectionState,
+}
+
+/// BrokerStatusCallback is used by the communication broker to report the connection state
+/// of its endpoint as the connection is established, lost and retried
+#[derive(Clone, Debug)]
+pub struct BrokerStatusCallback {
+    pub key: String,
+    pub sender: Sender<BrokerStatus>,
+}
+
+impl BrokerStatusCallback {
+    pub async fn send_state(&self, state: BrokerConnectionState) {
+        let status = BrokerStatus {
+            key: self.key.clone(),
+            state,
+        };
+        if let Err(e) = self.sender.send(status).await {
+            error!("couldnt send broker status {:?}", e);
+        }
+    }
+}
+
 static ATOMIC_ID: AtomicU64 = AtomicU64::new(0);
 
+/// Interval in which outstanding broker requests are checked for an expired timeout
+const BROKER_TIMEOUT_REAP_INTERVAL_MS: u64 = 100;
+const REQUIRED_ENDPOINT_POLL_INTERVAL_MS: u64 = 100;
+const BROKER_LATENCY_TIMER: &str = "broker_round_trip";
+/// Number of latest round trips kept per endpoint for [BrokerLatencySummary]
+const BROKER_LATENCY_WINDOW: usize = 100;
+
+/// Round trip latency of the latest requests brokered to an endpoint
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokerLatencySummary {
+    pub samples: usize,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub avg_ms: u64,
+    pub last_ms: u64,
+}
+
+impl BrokerLatencySummary {
+    fn from_window(window: &VecDeque<u64>) -> Self {
+        let samples = window.len();
+        if samples == 0 {
+            return Self::default();
+        }
+        BrokerLatencySummary {
+            samples,
+            min_ms: window.iter().copied().min().unwrap_or_default(),
+            max_ms: window.iter().copied().max().unwrap_or_default(),
+            avg_ms: window.iter().sum::<u64>() / samples as u64,
+            last_ms: window.back().copied().unwrap_or_default(),
+        }
+    }
+}
+
 impl BrokerCallback {
     /// Default method used for sending errors via the BrokerCallback
     pub async fn send_error(&self, request: BrokerRequest, error: RippleError) {
+        let code = match error {
+            RippleError::TimedOut => JSON_RPC_SERVER_ERROR_TIMED_OUT,
+            _ => JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+        };
         let value = serde_json::to_value(JsonRpcError {
-            code: JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+            code,
             message: format!("Error with {:?}", error),
             data: None,
         })
         .unwrap();
-        let data = JsonRpcApiResponse {
-            jsonrpc: "2.0".to_owned(),
-            id: Some(request.rpc.ctx.call_id),
-            error: Some(value),
-            result: None,
-            method: None,
-            params: None,
-        };
+        let data = JsonRpcApiResponse::error(Some(request.rpc.ctx.call_id), value);
         let output = BrokerOutput { data };
         if let Err(e) = self.sender.send(output).await {
             error!("couldnt send error for {:?}", e);
@@ -219,13 +297,51 @@ impl From<CallContext> for BrokerContext {
 }
 
 impl BrokerSender {
+    pub fn new(sender: Sender<BrokerRequest>) -> Self {
+        Self {
+            sender,
+            reject_when_full: false,
+            overflowed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Sender which reports a full channel back to the caller instead of waiting for the broker
+    /// to catch up.
+    pub fn with_backpressure(sender: Sender<BrokerRequest>) -> Self {
+        Self {
+            reject_when_full: true,
+            ..Self::new(sender)
+        }
+    }
+
     // Method to send the request to the underlying broker for handling.
     pub async fn send(&self, request: BrokerRequest) -> RippleResponse {
-        if let Err(e) = self.sender.send(request).await {
-            error!("Error sending to broker {:?}", e);
-            Err(RippleError::SendFailure)
-        } else {
-            Ok(())
+        if !self.reject_when_full {
+            if let Err(e) = self.sender.send(request).await {
+                error!("Error sending to broker {:?}", e);
+                return Err(RippleError::SendFailure);
+            }
+            return Ok(());
+        }
+        match self.sender.try_send(request) {
+            Ok(_) => {
+                // warn again on the next overflow
+                self.overflowed.store(false, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(TrySendError::Full(request)) => {
+                if !self.overflowed.swap(true, Ordering::Relaxed) {
+                    warn!(
+                        "Broker channel is full, rejecting requests until the endpoint catches up. request={:?}",
+                        request
+                    );
+                }
+                Err(RippleError::BrokerError("backpressure".to_owned()))
+            }
+            Err(TrySendError::Closed(e)) => {
+                error!("Error sending to broker {:?}", e);
+                Err(RippleError::SendFailure)
+            }
         }
     }
 }
@@ -239,6 +355,11 @@ pub struct EndpointBrokerState {
     rule_engine: RuleEngine,
     cleaner_list: Arc<RwLock<Vec<BrokerCleaner>>>,
     reconnect_tx: Sender<BrokerConnectRequest>,
+    request_deadlines: Arc<RwLock<HashMap<u64, Instant>>>,
+    status_tx: Sender<BrokerStatus>,
+    endpoint_status: Arc<RwLock<HashMap<String, BrokerConnectionState>>>,
+    request_timers: Arc<RwLock<HashMap<u64, Timer>>>,
+    latency_windows: Arc<RwLock<HashMap<String, VecDeque<u64>>>>,
 }
 
 impl EndpointBrokerState {
@@ -248,6 +369,7 @@ impl EndpointBrokerState {
         ripple_client: RippleClient,
     ) -> Self {
         let (reconnect_tx, rec_tr) = mpsc::channel(2);
+        let (status_tx, status_tr) = mpsc::channel(10);
         let state = Self {
             endpoint_map: Arc::new(RwLock::new(HashMap::new())),
             callback: BrokerCallback { sender: tx },
@@ -256,11 +378,178 @@ impl EndpointBrokerState {
             rule_engine,
             cleaner_list: Arc::new(RwLock::new(Vec::new())),
             reconnect_tx,
+            request_deadlines: Arc::new(RwLock::new(HashMap::new())),
+            status_tx,
+            endpoint_status: Arc::new(RwLock::new(HashMap::new())),
+            request_timers: Arc::new(RwLock::new(HashMap::new())),
+            latency_windows: Arc::new(RwLock::new(HashMap::new())),
         };
         state.reconnect_thread(rec_tr, ripple_client);
+        state.timeout_reaper_thread();
+        state.status_thread(status_tr);
         state
     }
 
+    /// Keeps track of the latest connection state reported by each broker
+    fn status_thread(&self, mut rx: Receiver<BrokerStatus>) {
+        let endpoint_status = self.endpoint_status.clone();
+        tokio::spawn(async move {
+            while let Some(status) = rx.recv().await {
+                info!("Broker {} is {:?}", status.key, status.state);
+                let _ = endpoint_status
+                    .write()
+                    .unwrap()
+                    .insert(status.key, status.state);
+            }
+        });
+    }
+
+    /// Returns the last known connection state of the broker for the given endpoint key
+    pub fn get_endpoint_status(&self, key: &str) -> Option<BrokerConnectionState> {
+        self.endpoint_status.read().unwrap().get(key).cloned()
+    }
+
+    /// Waits until every endpoint marked as `required` has connected. Fails with
+    /// [RippleError::TimedOut] if any of them is still down once `timeout` elapses.
+    pub async fn wait_for_required_endpoints(&self, timeout: Duration) -> RippleResponse {
+        let required: Vec<String> = self
+            .rule_engine
+            .rules
+            .endpoints
+            .iter()
+            .filter(|(_, endpoint)| endpoint.required)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let deadline = Instant::now() + timeout;
+        loop {
+            let pending: Vec<&String> = required
+                .iter()
+                .filter(|key| {
+                    self.get_endpoint_status(key) != Some(BrokerConnectionState::Connected)
+                })
+                .collect();
+            if pending.is_empty() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                error!(
+                    "Required endpoints {:?} did not connect within {}ms",
+                    pending,
+                    timeout.as_millis()
+                );
+                return Err(RippleError::TimedOut);
+            }
+            tokio::time::sleep(Duration::from_millis(REQUIRED_ENDPOINT_POLL_INTERVAL_MS)).await;
+        }
+    }
+
+    /// Periodically fails the requests whose endpoint did not respond within the configured
+    /// timeout. The timeout error is sent through the callback like any other broker response.
+    fn timeout_reaper_thread(&self) {
+        let state = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(BROKER_TIMEOUT_REAP_INTERVAL_MS)).await;
+                state.prune_request_timers();
+                for request in state.reap_timed_out_requests() {
+                    error!(
+                        "Broker request timed out method={} alias={}",
+                        request.rpc.method, request.rule.alias
+                    );
+                    state
+                        .callback
+                        .send_error(request, RippleError::TimedOut)
+                        .await;
+                }
+            }
+        });
+    }
+
+    fn start_request_timer(&self, id: u64, endpoint: String) {
+        let tags = HashMap::from([("endpoint".to_owned(), endpoint)]);
+        let timer = Timer::start(BROKER_LATENCY_TIMER.to_owned(), Some(tags), None);
+        let _ = self.request_timers.write().unwrap().insert(id, timer);
+    }
+
+    /// Stops the round trip timer of a brokered request once its response arrived and adds
+    /// the latency to the rolling summary of the endpoint. Requests which timed out are left
+    /// out of the summary as their latency is the timeout and not a round trip.
+    pub fn stop_request_timer(&self, id: u64, timed_out: bool) -> Option<Timer> {
+        let mut timer = self.request_timers.write().unwrap().remove(&id)?;
+        timer.stop();
+        if timed_out {
+            return Some(timer);
+        }
+        if let Some(endpoint) = timer.tags.as_ref().and_then(|tags| tags.get("endpoint")) {
+            let mut latency_windows = self.latency_windows.write().unwrap();
+            let window = latency_windows.entry(endpoint.clone()).or_default();
+            if window.len() == BROKER_LATENCY_WINDOW {
+                window.pop_front();
+            }
+            window.push_back(timer.elapsed().as_millis() as u64);
+        }
+        Some(timer)
+    }
+
+    /// Drops the timers whose request is gone without its response being forwarded
+    fn prune_request_timers(&self) {
+        let request_map = self.request_map.read().unwrap();
+        self.request_timers
+            .write()
+            .unwrap()
+            .retain(|id, _| request_map.contains_key(id));
+    }
+
+    /// Returns the rolling round trip latency summary for each endpoint
+    pub fn get_latency_summary(&self) -> HashMap<String, BrokerLatencySummary> {
+        self.latency_windows
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(endpoint, window)| (endpoint.clone(), BrokerLatencySummary::from_window(window)))
+            .collect()
+    }
+
+    fn add_deadline(&self, id: u64, timeout: Duration) {
+        let mut deadlines = self.request_deadlines.write().unwrap();
+        let _ = deadlines.insert(id, Instant::now() + timeout);
+    }
+
+    fn reap_timed_out_requests(&self) -> Vec<BrokerRequest> {
+        let now = Instant::now();
+        let expired: Vec<u64> = {
+            let mut deadlines = self.request_deadlines.write().unwrap();
+            let expired = deadlines
+                .iter()
+                .filter(|(_, deadline)| **deadline <= now)
+                .map(|(id, _)| *id)
+                .collect::<Vec<u64>>();
+            for id in expired.iter() {
+                let _ = deadlines.remove(id);
+            }
+            expired
+        };
+        let request_map = self.request_map.read().unwrap();
+        expired
+            .into_iter()
+            .filter_map(|id| {
+                request_map.get(&id).cloned().map(|mut request| {
+                    // respond with the broker id so the forwarder can find the caller
+                    request.rpc.ctx.call_id = id;
+                    request
+                })
+            })
+            .collect()
+    }
+
+    fn get_endpoint_timeout(&self, key: &str) -> Option<Duration> {
+        self.rule_engine
+            .rules
+            .endpoints
+            .get(key)
+            .and_then(|endpoint| endpoint.get_timeout())
+    }
+
     fn reconnect_thread(&self, mut rx: Receiver<BrokerConnectRequest>, client: RippleClient) {
         let mut state = self.clone();
         tokio::spawn(async move {
@@ -289,6 +578,7 @@ impl EndpointBrokerState {
         let result = result.unwrap();
         if !result.rpc.is_subscription() {
             let _ = self.request_map.write().unwrap().remove(&id);
+            let _ = self.request_deadlines.write().unwrap().remove(&id);
         }
         Ok(result)
     }
@@ -318,9 +608,11 @@ impl EndpointBrokerState {
         }
     }
 
+    /// Returns a broker unique id. The increment and the read have to be a single atomic
+    /// operation, otherwise two overlapping requests could be assigned the same id and
+    /// receive each other's responses.
     pub fn get_next_id() -> u64 {
-        ATOMIC_ID.fetch_add(1, Ordering::Relaxed);
-        ATOMIC_ID.load(Ordering::Relaxed)
+        ATOMIC_ID.fetch_add(1, Ordering::Relaxed) + 1
     }
 
     fn update_request(
@@ -375,9 +667,13 @@ impl EndpointBrokerState {
         }
     }
 
-    fn build_endpoint(&mut self, request: BrokerConnectRequest) {
+    fn build_endpoint(&mut self, mut request: BrokerConnectRequest) {
         let endpoint = request.endpoint.clone();
         let key = request.key.clone();
+        request.status_callback = Some(BrokerStatusCallback {
+            key: key.clone(),
+            sender: self.status_tx.clone(),
+        });
         let (broker, cleaner) = match endpoint.protocol {
             RuleEndpointProtocol::Http => (
                 HttpBroker::get_broker(request, self.callback.clone()).get_sender(),
@@ -415,11 +711,8 @@ impl EndpointBrokerState {
         callback: BrokerCallback,
     ) {
         let (id, _updated_request) = self.update_request(&rpc_request, rule.clone(), extn_message);
-        let mut data = JsonRpcApiResponse::default();
         // return empty result and handle the rest with jq rule
-        let jv: Value = "".into();
-        data.result = Some(jv);
-        data.id = Some(id);
+        let data = JsonRpcApiResponse::success(Some(id), "".into());
         let output = BrokerOutput { data };
         tokio::spawn(async move { callback.sender.send(output).await });
     }
@@ -439,15 +732,20 @@ impl EndpointBrokerState {
         let callback = self.callback.clone();
         let mut broker_sender = None;
         let mut found_rule = None;
+        let mut timeout = None;
+        let mut endpoint_key = String::from("thunder");
         if let Some(rule) = self.rule_engine.get_rule(&rpc_request) {
             let _ = found_rule.insert(rule.clone());
             if let Some(endpoint) = rule.endpoint {
-                if let Some(endpoint) = self.get_sender(&endpoint) {
-                    let _ = broker_sender.insert(endpoint);
+                if let Some(sender) = self.get_sender(&endpoint) {
+                    let _ = broker_sender.insert(sender);
+                    timeout = self.get_endpoint_timeout(&endpoint);
+                    endpoint_key = endpoint;
                 }
             } else if rule.alias != "static" {
-                if let Some(endpoint) = self.get_sender("thunder") {
-                    let _ = broker_sender.insert(endpoint);
+                if let Some(sender) = self.get_sender("thunder") {
+                    let _ = broker_sender.insert(sender);
+                    timeout = self.get_endpoint_timeout("thunder");
                 }
             }
         }
@@ -459,7 +757,17 @@ impl EndpointBrokerState {
                 self.handle_static_request(rpc_request, extn_message, rule, callback);
             } else if broker_sender.is_some() {
                 let broker = broker_sender.unwrap();
-                let (_, updated_request) = self.update_request(&rpc_request, rule, extn_message);
+                let (id, updated_request) = self.update_request(&rpc_request, rule, extn_message);
+                debug!(
+                    "brokering method={} id={} trace_id={:?}",
+                    rpc_request.method, id, rpc_request.ctx.trace_id
+                );
+                if let Some(timeout) = timeout {
+                    if !rpc_request.is_subscription() {
+                        self.add_deadline(id, timeout);
+                    }
+                }
+                self.start_request_timer(id, endpoint_key);
                 tokio::spawn(async move {
                     if let Err(e) = broker.send(updated_request.clone()).await {
                         callback.send_error(updated_request, e).await
@@ -600,6 +908,20 @@ impl BrokerOutputForwarder {
                 };
 
                 if let Some(id) = id {
+                    // the timer goes away along with the request, so stop it first
+                    let latency_timer = if is_event {
+                        None
+                    } else {
+                        let timed_out = response
+                            .error
+                            .as_ref()
+                            .and_then(|e| e.get("code"))
+                            .and_then(Value::as_i64)
+                            == Some(JSON_RPC_SERVER_ERROR_TIMED_OUT as i64);
+                        platform_state
+                            .endpoint_state
+                            .stop_request_timer(id, timed_out)
+                    };
                     if let Ok(broker_request) = platform_state.endpoint_state.get_request(id) {
                         let sub_processed = broker_request.is_subscription_processed();
                         let rpc_request = broker_request.rpc.clone();
@@ -714,6 +1036,19 @@ impl BrokerOutputForwarder {
                             }
                         }
 
+                        if let Some(mut timer) = latency_timer {
+                            timer.insert_tag(Tag::Status.key(), status_code.to_string());
+                            let client = platform_state.get_client();
+                            tokio::spawn(async move {
+                                if let Err(e) = client
+                                    .send_extn_request(OperationalMetricRequest::Timer(timer))
+                                    .await
+                                {
+                                    error!("start_forwarder: send latency timer={:?}", e)
+                                }
+                            });
+                        }
+
                         message.stats = Some(ApiStats {
                             stats_ref: add_telemetry_status_code(
                                 &tm_str,
@@ -777,16 +1112,10 @@ impl BrokerOutputForwarder {
         if parse_result.is_err() {
             return Err(RippleError::ParseError);
         }
-        let result = Some(parse_result.unwrap());
         // build JsonRpcApiResponse
-        let data = JsonRpcApiResponse {
-            jsonrpc: "2.0".to_owned(),
-            id: Some(request.rpc.ctx.call_id),
-            method,
-            result,
-            error: None,
-            params: None,
-        };
+        let mut data =
+            JsonRpcApiResponse::success(Some(request.rpc.ctx.call_id), parse_result.unwrap());
+        data.method = method;
         let output = BrokerOutput { data };
         tokio::spawn(async move { callback.sender.send(output).await });
         Ok(())
@@ -922,6 +1251,49 @@ mod tests {
         assert!(value.data.error.is_some())
     }
 
+    fn broker_request() -> BrokerRequest {
+        BrokerRequest {
+            rpc: RpcRequest::mock(),
+            rule: Rule {
+                alias: "somecallsign.method".to_owned(),
+                transform: RuleTransform::default(),
+                endpoint: None,
+                filter: None,
+            },
+            subscription_processed: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_channel_waits_without_backpressure() {
+        let (tx, mut tr) = channel(1);
+        let sender = BrokerSender::new(tx);
+        assert!(sender.send(broker_request()).await.is_ok());
+
+        let pending = tokio::spawn(async move { sender.send(broker_request()).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!pending.is_finished());
+
+        assert!(tr.recv().await.is_some());
+        assert!(pending.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_backpressure_overflow_is_cleared_after_send() {
+        let (tx, mut tr) = channel(1);
+        let sender = BrokerSender::with_backpressure(tx);
+        assert!(sender.send(broker_request()).await.is_ok());
+        assert!(matches!(
+            sender.send(broker_request()).await,
+            Err(RippleError::BrokerError(_))
+        ));
+        assert!(sender.overflowed.load(Ordering::Relaxed));
+
+        assert!(tr.recv().await.is_some());
+        assert!(sender.send(broker_request()).await.is_ok());
+        assert!(!sender.overflowed.load(Ordering::Relaxed));
+    }
+
     mod broker_output {
         use ripple_sdk::{api::gateway::rpc_gateway_api::JsonRpcApiResponse, Mockable};
 
@@ -947,14 +1319,28 @@ mod tests {
     }
 
     mod endpoint_broker_state {
+        use std::{collections::HashMap, time::Duration};
+
+        use futures::{SinkExt, StreamExt};
         use ripple_sdk::{
-            api::gateway::rpc_gateway_api::RpcRequest, tokio, tokio::sync::mpsc::channel, Mockable,
+            api::{
+                firebolt::fb_capabilities::JSON_RPC_SERVER_ERROR_TIMED_OUT,
+                gateway::rpc_gateway_api::RpcRequest,
+            },
+            tokio,
+            tokio::sync::mpsc::channel,
+            utils::error::RippleError,
+            Mockable,
         };
+        use serde_json::{json, Value};
+        use tokio_tungstenite::tungstenite::Message;
 
         use crate::{
             broker::{
-                endpoint_broker::tests::RippleClient,
-                rules_engine::{Rule, RuleEngine, RuleSet, RuleTransform},
+                endpoint_broker::{tests::RippleClient, BrokerConnectionState, BrokerSender},
+                rules_engine::{
+                    Rule, RuleEndpoint, RuleEndpointProtocol, RuleEngine, RuleSet, RuleTransform,
+                },
             },
             state::bootstrap_state::ChannelsState,
         };
@@ -1001,6 +1387,362 @@ mod tests {
             // assert!(state.get_request(2).is_ok());
             // assert!(state.get_request(1).is_ok());
         }
+
+        #[tokio::test]
+        async fn overlapping_requests_are_correlated() {
+            let (tx, _) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let state = EndpointBrokerState::new(
+                tx,
+                RuleEngine {
+                    rules: RuleSet::default(),
+                },
+                client,
+            );
+            let rule = Rule {
+                alias: "somecallsign.method".to_owned(),
+                transform: RuleTransform::default(),
+                endpoint: None,
+                filter: None,
+            };
+            // Two apps using the same request id towards the same endpoint
+            let mut first = RpcRequest::mock();
+            first.ctx.call_id = 7;
+            first.ctx.session_id = "first_session".to_owned();
+            let mut second = RpcRequest::mock();
+            second.ctx.call_id = 7;
+            second.ctx.session_id = "second_session".to_owned();
+
+            let (first_id, first_broker) = state.update_request(&first, rule.clone(), None);
+            let (second_id, second_broker) = state.update_request(&second, rule, None);
+            assert_ne!(first_id, second_id);
+            assert_eq!(first_broker.rpc.ctx.call_id, first_id);
+            assert_eq!(second_broker.rpc.ctx.call_id, second_id);
+
+            // Replies arriving out of order are restored to their own callers
+            let second_request = state.get_request(second_id).unwrap();
+            assert_eq!(second_request.rpc.ctx.session_id, "second_session");
+            assert_eq!(second_request.rpc.ctx.call_id, 7);
+            let first_request = state.get_request(first_id).unwrap();
+            assert_eq!(first_request.rpc.ctx.session_id, "first_session");
+            assert_eq!(first_request.rpc.ctx.call_id, 7);
+        }
+
+        #[tokio::test]
+        async fn request_to_silent_endpoint_times_out() {
+            let (tx, mut rx) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let mut rules = RuleSet::default();
+            rules.endpoints.insert(
+                "silent".to_owned(),
+                RuleEndpoint {
+                    protocol: RuleEndpointProtocol::Websocket,
+                    url: "ws://127.0.0.1:0".to_owned(),
+                    jsonrpc: true,
+                    http_jsonrpc: false,
+                    timeout_ms: Some(50),
+                    headers: HashMap::new(),
+                    query_params: HashMap::new(),
+                    channel_capacity: None,
+                    required: false,
+                },
+            );
+            rules.rules.insert(
+                "module.method".to_owned(),
+                Rule {
+                    alias: "somecallsign.method".to_owned(),
+                    transform: RuleTransform::default(),
+                    endpoint: Some("silent".to_owned()),
+                    filter: None,
+                },
+            );
+            let state = EndpointBrokerState::new(tx, RuleEngine { rules }, client);
+            // Endpoint which accepts requests but never answers
+            let (endpoint_tx, _endpoint_rx) = channel(2);
+            state
+                .endpoint_map
+                .write()
+                .unwrap()
+                .insert("silent".to_owned(), BrokerSender::new(endpoint_tx));
+
+            assert!(state.handle_brokerage(RpcRequest::mock(), None));
+            let output = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            let error = output.data.error.unwrap();
+            assert_eq!(
+                error.get("code").unwrap().as_i64().unwrap(),
+                JSON_RPC_SERVER_ERROR_TIMED_OUT as i64
+            );
+            assert!(state.request_deadlines.read().unwrap().is_empty());
+        }
+
+        fn websocket_endpoint(port: u16, required: bool) -> RuleEndpoint {
+            RuleEndpoint {
+                protocol: RuleEndpointProtocol::Websocket,
+                url: format!("ws://127.0.0.1:{}", port),
+                jsonrpc: true,
+                http_jsonrpc: false,
+                timeout_ms: None,
+                headers: HashMap::new(),
+                query_params: HashMap::new(),
+                channel_capacity: None,
+                required,
+            }
+        }
+
+        fn unused_port() -> u16 {
+            std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port()
+        }
+
+        #[tokio::test]
+        async fn required_endpoint_is_awaited_until_connected() {
+            let (tx, _rx) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let required_port = unused_port();
+            let mut rules = RuleSet::default();
+            rules.endpoints.insert(
+                "required".to_owned(),
+                websocket_endpoint(required_port, true),
+            );
+            rules.endpoints.insert(
+                "optional".to_owned(),
+                websocket_endpoint(unused_port(), false),
+            );
+            let mut state = EndpointBrokerState::new(tx, RuleEngine { rules }, client);
+            state.build_other_endpoints(None);
+
+            // Required endpoint is down
+            assert!(matches!(
+                state
+                    .wait_for_required_endpoints(Duration::from_millis(200))
+                    .await,
+                Err(RippleError::TimedOut)
+            ));
+
+            let waiting = {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    state
+                        .wait_for_required_endpoints(Duration::from_secs(10))
+                        .await
+                })
+            };
+            // Required endpoint comes up, the optional one stays down
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", required_port))
+                .await
+                .unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let result = tokio::time::timeout(Duration::from_secs(10), waiting)
+                .await
+                .expect("required endpoint was never reported as connected")
+                .unwrap();
+            assert!(result.is_ok());
+            assert_eq!(
+                state.get_endpoint_status("required"),
+                Some(BrokerConnectionState::Connected)
+            );
+            assert_eq!(state.get_endpoint_status("optional"), None);
+        }
+
+        #[tokio::test]
+        async fn required_http_endpoint_is_connected() {
+            let (tx, _rx) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let mut rules = RuleSet::default();
+            rules.endpoints.insert(
+                "http".to_owned(),
+                RuleEndpoint {
+                    protocol: RuleEndpointProtocol::Http,
+                    url: format!("http://127.0.0.1:{}", unused_port()),
+                    jsonrpc: false,
+                    http_jsonrpc: false,
+                    timeout_ms: None,
+                    headers: HashMap::new(),
+                    query_params: HashMap::new(),
+                    channel_capacity: None,
+                    required: true,
+                },
+            );
+            let mut state = EndpointBrokerState::new(tx, RuleEngine { rules }, client);
+            state.build_other_endpoints(None);
+
+            assert!(state
+                .wait_for_required_endpoints(Duration::from_secs(5))
+                .await
+                .is_ok());
+            assert_eq!(
+                state.get_endpoint_status("http"),
+                Some(BrokerConnectionState::Connected)
+            );
+        }
+
+        /// Endpoint which answers every request with a null result after `delay`
+        async fn delayed_endpoint(delay: Duration) -> u16 {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(Message::Text(request))) = ws.next().await {
+                    let request: Value = serde_json::from_str(&request).unwrap();
+                    tokio::time::sleep(delay).await;
+                    let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": null});
+                    ws.send(Message::Text(response.to_string())).await.unwrap();
+                }
+            });
+            port
+        }
+
+        #[tokio::test]
+        async fn round_trip_latency_is_measured() {
+            let delay = Duration::from_millis(150);
+            let port = delayed_endpoint(delay).await;
+            let (tx, mut rx) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let mut rules = RuleSet::default();
+            rules
+                .endpoints
+                .insert("delayed".to_owned(), websocket_endpoint(port, true));
+            rules.rules.insert(
+                "module.method".to_owned(),
+                Rule {
+                    alias: "somecallsign.method".to_owned(),
+                    transform: RuleTransform::default(),
+                    endpoint: Some("delayed".to_owned()),
+                    filter: None,
+                },
+            );
+            let mut state = EndpointBrokerState::new(tx, RuleEngine { rules }, client);
+            state.build_other_endpoints(None);
+            state
+                .wait_for_required_endpoints(Duration::from_secs(10))
+                .await
+                .unwrap();
+
+            assert!(state.handle_brokerage(RpcRequest::mock(), None));
+            let output = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            let id = output.data.id.unwrap();
+            let timer = state.stop_request_timer(id, false).unwrap();
+            state.get_request(id).unwrap();
+
+            assert!(timer.elapsed() >= delay);
+            assert_eq!(
+                timer.tags.unwrap().get("endpoint"),
+                Some(&"delayed".to_owned())
+            );
+            let summary = state.get_latency_summary();
+            let summary = summary.get("delayed").unwrap();
+            assert_eq!(summary.samples, 1);
+            assert!(summary.min_ms >= delay.as_millis() as u64);
+            assert_eq!(summary.min_ms, summary.last_ms);
+            // The timer is only reported once
+            assert!(state.stop_request_timer(id, false).is_none());
+        }
+
+        #[tokio::test]
+        async fn timed_out_request_is_not_summarised() {
+            let (tx, _rx) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let state = EndpointBrokerState::new(tx, RuleEngine::default(), client);
+            state.start_request_timer(1, "silent".to_owned());
+
+            assert!(state.stop_request_timer(1, true).is_some());
+            assert!(state.get_latency_summary().get("silent").is_none());
+        }
+
+        #[tokio::test]
+        async fn timer_without_request_is_pruned() {
+            let (tx, _rx) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let state = EndpointBrokerState::new(tx, RuleEngine::default(), client);
+            let rule = Rule {
+                alias: "somecallsign.method".to_owned(),
+                transform: RuleTransform::default(),
+                endpoint: Some("pending".to_owned()),
+                filter: None,
+            };
+            let (id, _) = state.update_request(&RpcRequest::mock(), rule, None);
+            state.start_request_timer(id, "pending".to_owned());
+            state.start_request_timer(id + 1, "orphaned".to_owned());
+
+            state.prune_request_timers();
+            let timers = state.request_timers.read().unwrap();
+            assert!(timers.contains_key(&id));
+            assert!(!timers.contains_key(&(id + 1)));
+        }
+
+        #[tokio::test]
+        async fn trace_id_is_carried_to_broker_request() {
+            let (tx, _rx) = channel(2);
+            let client = RippleClient::new(ChannelsState::new());
+            let mut rules = RuleSet::default();
+            rules.rules.insert(
+                "module.method".to_owned(),
+                Rule {
+                    alias: "somecallsign.method".to_owned(),
+                    transform: RuleTransform::default(),
+                    endpoint: None,
+                    filter: None,
+                },
+            );
+            let state = EndpointBrokerState::new(tx, RuleEngine { rules }, client);
+            let (endpoint_tx, mut endpoint_rx) = channel(2);
+            state
+                .endpoint_map
+                .write()
+                .unwrap()
+                .insert("thunder".to_owned(), BrokerSender::new(endpoint_tx));
+
+            let request = RpcRequest::parse(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "module.method", "params": {}})
+                    .to_string(),
+                "app_id".to_owned(),
+                "session_id".to_owned(),
+                "request_id".to_owned(),
+                None,
+                true,
+            )
+            .unwrap();
+            let trace_id = request.ctx.trace_id.clone();
+            assert!(trace_id.is_some());
+
+            assert!(state.handle_brokerage(request, None));
+            let broker_request = tokio::time::timeout(Duration::from_secs(2), endpoint_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(broker_request.rpc.ctx.trace_id, trace_id);
+        }
+
+        #[test]
+        fn next_id_is_unique_across_threads() {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    std::thread::spawn(|| {
+                        (0..1000)
+                            .map(|_| EndpointBrokerState::get_next_id())
+                            .collect::<Vec<u64>>()
+                    })
+                })
+                .collect();
+            let mut ids = std::collections::HashSet::new();
+            for handle in handles {
+                for id in handle.join().unwrap() {
+                    assert!(ids.insert(id));
+                }
+            }
+        }
     }
 
     #[tokio::test]
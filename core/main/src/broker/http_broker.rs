@@ -19,6 +19,7 @@ use std::vec;
 
 use hyper::{client::HttpConnector, Body, Client, Method, Request, Response, Uri};
 use ripple_sdk::{
+    api::gateway::rpc_gateway_api::JsonRpcApiResponse,
     log::{debug, error, trace},
     tokio::{self, sync::mpsc},
     utils::error::RippleError,
@@ -27,8 +28,8 @@ use ripple_sdk::{
 use tokio_tungstenite::tungstenite::http::uri::InvalidUri;
 
 use super::endpoint_broker::{
-    BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerOutputForwarder, BrokerRequest,
-    BrokerSender, EndpointBroker,
+    BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerConnectionState, BrokerOutput,
+    BrokerOutputForwarder, BrokerRequest, BrokerSender, EndpointBroker,
 };
 
 pub struct HttpBroker {
@@ -44,10 +45,8 @@ async fn send_http_request(
     method: Method,
     uri: &Uri,
     path: &str,
+    body: Body,
 ) -> Result<Response<Body>, RippleError> {
-    /*
-    TODO? we may need to support body for POST request in the future
-    */
     let http_request = Request::new(Body::empty());
     let (mut parts, _) = http_request.into_parts();
     //TODO, need to refactor to support other methods
@@ -69,8 +68,14 @@ async fn send_http_request(
     let (uri_parts, _) = new_request.into_parts();
 
     parts.uri = uri_parts.uri;
+    if method == Method::POST {
+        parts.headers.insert(
+            hyper::header::CONTENT_TYPE,
+            hyper::header::HeaderValue::from_static("application/json"),
+        );
+    }
 
-    let http_request = Request::from_parts(parts, Body::empty());
+    let http_request = Request::from_parts(parts, body);
 
     debug!(
         "http_broker sending {} request={}",
@@ -118,13 +123,48 @@ async fn body_to_bytes(body: Body) -> Vec<u8> {
 impl EndpointBroker for HttpBroker {
     fn get_broker(request: BrokerConnectRequest, callback: BrokerCallback) -> Self {
         let endpoint = request.endpoint.clone();
-        let (tx, mut tr) = mpsc::channel(10);
-        let broker = BrokerSender { sender: tx };
+        let (tx, mut tr) = mpsc::channel(endpoint.get_channel_capacity());
+        let broker = BrokerSender::new(tx);
         let client = Client::new();
+        let status = request.status_callback.clone();
         let _ =  endpoint.get_url().parse().map_err(|e| error!("broker url {:?} in endpoint is invalid, cannot start http broker. error={}",endpoint,e) ).map(|uri| tokio::spawn(async move {
+            // Http is connectionless, the endpoint is usable as soon as its url is valid
+            if let Some(status) = status {
+                status.send_state(BrokerConnectionState::Connected).await;
+            }
             while let Some(request) = tr.recv().await {
                 debug!("http broker received request={:?}", request);
-                match send_http_request(&client, Method::GET, &uri, &request.clone().rule.alias)
+                if endpoint.http_jsonrpc {
+                    // JSON-RPC endpoints get the request posted as body and answer with a JSON-RPC response
+                    match Self::update_request(&request) {
+                        Ok(jsonrpc_request) => {
+                            match send_http_request(&client, Method::POST, &uri, "", Body::from(jsonrpc_request)).await {
+                                Ok(response) => {
+                                    let status = response.status();
+                                    let body = body_to_bytes(response.into_body()).await;
+                                    if !status.is_success() {
+                                        error!("http error {} returned from jsonrpc http service={} in http broker {:?}", status, uri, String::from_utf8_lossy(&body));
+                                        callback.send_error(request, RippleError::BrokerError(format!("http error {}", status))).await;
+                                    } else if let Ok(data) = serde_json::from_slice::<JsonRpcApiResponse>(&body) {
+                                        if let Err(e) = callback.sender.send(BrokerOutput { data }).await {
+                                            error!("couldnt send jsonrpc http response {:?}", e);
+                                        }
+                                    } else {
+                                        error!("Bad jsonrpc response from http service={} in http broker {}", uri, String::from_utf8_lossy(&body));
+                                        callback.send_error(request, RippleError::ParseError).await;
+                                    }
+                                }
+                                Err(e) => {
+                                    error!("An error message from calling the downstream jsonrpc http service={} in http broker {:?}", uri, e);
+                                    callback.send_error(request, e).await;
+                                }
+                            }
+                        }
+                        Err(e) => callback.send_error(request, e).await,
+                    }
+                    continue;
+                }
+                match send_http_request(&client, Method::GET, &uri, &request.clone().rule.alias, Body::empty())
                     .await
                 {
                     Ok(response) => {
@@ -177,3 +217,148 @@ impl EndpointBroker for HttpBroker {
         self.cleaner.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use super::*;
+    use crate::broker::{
+        endpoint_broker::BrokerOutput,
+        rules_engine::{Rule, RuleEndpoint, RuleEndpointProtocol, RuleTransform},
+    };
+    use ripple_sdk::{
+        api::gateway::rpc_gateway_api::RpcRequest,
+        tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        },
+    };
+    use serde_json::{json, Value};
+
+    /// Minimal http server answering a single JSON-RPC POST with the id of the request
+    async fn start_jsonrpc_http_server(result: Value) -> u16 {
+        start_http_server("200 OK", move |body| {
+            json!({"jsonrpc": "2.0", "id": body["id"], "result": result}).to_string()
+        })
+        .await
+    }
+
+    /// Minimal http server answering a single POST with the given status and the response
+    /// built from the posted JSON body
+    async fn start_http_server(
+        status: &'static str,
+        response: impl FnOnce(&Value) -> String + Send + 'static,
+    ) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 1024];
+            let request = loop {
+                let n = stream.read(&mut chunk).await.unwrap();
+                buffer.extend_from_slice(&chunk[..n]);
+                let text = String::from_utf8_lossy(&buffer).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().to_owned())
+                        })
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break (head.to_owned(), body.to_owned());
+                    }
+                }
+            };
+            assert!(request.0.starts_with("POST "));
+            let body: Value = serde_json::from_str(&request.1).unwrap();
+            let response = response(&body);
+            let _ = stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                        status,
+                        response.len(),
+                        response
+                    )
+                    .as_bytes(),
+                )
+                .await;
+        });
+        port
+    }
+
+    fn jsonrpc_endpoint(port: u16) -> RuleEndpoint {
+        RuleEndpoint {
+            protocol: RuleEndpointProtocol::Http,
+            url: format!("http://127.0.0.1:{}", port),
+            jsonrpc: true,
+            http_jsonrpc: true,
+            timeout_ms: None,
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            channel_capacity: None,
+            required: false,
+        }
+    }
+
+    /// Sends a request with call id 42 to a jsonrpc http broker on the given port and returns
+    /// the output of the broker
+    async fn jsonrpc_http_output(port: u16) -> BrokerOutput {
+        let (tx, _) = mpsc::channel(1);
+        let request = BrokerConnectRequest::new("somekey".to_owned(), jsonrpc_endpoint(port), tx);
+        let (sender, mut rec) = mpsc::channel::<BrokerOutput>(1);
+        let broker = HttpBroker::get_broker(request, BrokerCallback { sender });
+
+        let mut rpc = RpcRequest::get_new_internal("some_method".to_owned(), None);
+        rpc.ctx.call_id = 42;
+        let request = BrokerRequest {
+            rpc,
+            rule: Rule {
+                alias: "somecallsign.method".to_owned(),
+                transform: RuleTransform::default(),
+                endpoint: None,
+                filter: None,
+            },
+            subscription_processed: None,
+        };
+        broker.get_sender().send(request).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), rec.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn jsonrpc_http_request() {
+        let port = start_jsonrpc_http_server(json!({"success": true})).await;
+        let output = jsonrpc_http_output(port).await;
+        assert_eq!(output.data.id, Some(42));
+        assert_eq!(output.data.result, Some(json!({"success": true})));
+    }
+
+    #[tokio::test]
+    async fn jsonrpc_http_server_error_is_returned() {
+        let port = start_http_server("500 Internal Server Error", |body| {
+            json!({"jsonrpc": "2.0", "id": body["id"], "result": true}).to_string()
+        })
+        .await;
+        let output = jsonrpc_http_output(port).await;
+        assert_eq!(output.data.id, Some(42));
+        assert!(output.data.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn jsonrpc_http_unparsable_response_is_returned() {
+        let port = start_http_server("200 OK", |_| "not json".to_owned()).await;
+        let output = jsonrpc_http_output(port).await;
+        assert_eq!(output.data.id, Some(42));
+        assert!(output.data.error.is_some());
+    }
+}
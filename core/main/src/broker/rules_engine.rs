@@ -27,6 +27,7 @@ use ripple_sdk::{
 };
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::Duration;
 use std::{fs, path::Path};
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -56,9 +57,39 @@ pub struct RuleEndpoint {
     pub url: String,
     #[serde(default = "default_autostart")]
     pub jsonrpc: bool,
+    /// Http endpoints get the JSON-RPC request posted as the body, rather than a GET on the
+    /// alias of the rule
+    #[serde(default)]
+    pub http_jsonrpc: bool,
+    /// Time in milliseconds after which an unanswered request is failed with a timeout error
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Headers added to the websocket handshake e.g. `Authorization`
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Query params appended to the endpoint url
+    #[serde(default)]
+    pub query_params: HashMap<String, String>,
+    /// Number of requests which can be queued for the broker before callers get a backpressure error
+    #[serde(default)]
+    pub channel_capacity: Option<usize>,
+    /// Bootstrap waits for the first connection to this endpoint before the gateway accepts
+    /// requests. Other endpoints connect in the background.
+    #[serde(default)]
+    pub required: bool,
 }
 
 impl RuleEndpoint {
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn get_channel_capacity(&self) -> usize {
+        self.channel_capacity
+            .filter(|capacity| *capacity > 0)
+            .unwrap_or(DEFAULT_BROKER_CHANNEL_CAPACITY)
+    }
+
     pub fn get_url(&self) -> String {
         if cfg!(feature = "local_dev") {
             if let Ok(host_override) = std::env::var("DEVICE_HOST") {
@@ -71,6 +102,8 @@ impl RuleEndpoint {
     }
 }
 
+const DEFAULT_BROKER_CHANNEL_CAPACITY: usize = 10;
+
 fn default_autostart() -> bool {
     true
 }
@@ -249,7 +282,18 @@ pub fn jq_compile(input: Value, filter: &str, reference: String) -> Result<Value
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ripple_sdk::serde_json::json;
+    use ripple_sdk::serde_json::{self, json};
+
+    #[test]
+    fn test_http_endpoint_defaults_to_get() {
+        let endpoint: RuleEndpoint = serde_json::from_value(json!({
+            "protocol": "http",
+            "url": "http://127.0.0.1:8080"
+        }))
+        .unwrap();
+        assert!(endpoint.jsonrpc);
+        assert!(!endpoint.http_jsonrpc);
+    }
 
     #[test]
     fn test_jq_compile() {
@@ -531,7 +531,7 @@ mod tests {
     async fn test_on_activate_response() {
         let status_manager = StatusManager::new();
         let (tx, _tr) = mpsc::channel(10);
-        let broker = BrokerSender { sender: tx };
+        let broker = BrokerSender::new(tx);
 
         let (tx_1, _tr_1) = channel(2);
         let callback = BrokerCallback { sender: tx_1 };
@@ -556,7 +556,7 @@ mod tests {
     async fn test_on_status_response() {
         let status_manager = StatusManager::new();
         let (tx, _tr) = mpsc::channel(10);
-        let broker = BrokerSender { sender: tx };
+        let broker = BrokerSender::new(tx);
 
         let (tx_1, _tr_1) = channel(2);
         let callback = BrokerCallback { sender: tx_1 };
@@ -611,7 +611,7 @@ mod tests {
     async fn test_expired_broker_request() {
         let status_manager = StatusManager::new();
         let (tx, _tr) = mpsc::channel(10);
-        let broker = BrokerSender { sender: tx };
+        let broker = BrokerSender::new(tx);
 
         let (tx_1, _tr_1) = channel(2);
         let callback = BrokerCallback { sender: tx_1 };
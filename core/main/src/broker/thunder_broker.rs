@@ -16,8 +16,8 @@
 //
 use super::{
     endpoint_broker::{
-        BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerOutput, BrokerRequest,
-        BrokerSender, BrokerSubMap, EndpointBroker,
+        BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerConnectionState, BrokerOutput,
+        BrokerRequest, BrokerSender, BrokerSubMap, EndpointBroker,
     },
     thunder::thunder_plugins_status_mgr::StatusManager,
 };
@@ -47,9 +47,9 @@ pub struct ThunderBroker {
 impl ThunderBroker {
     fn start(request: BrokerConnectRequest, callback: BrokerCallback) -> Self {
         let endpoint = request.endpoint.clone();
-        let (tx, mut tr) = mpsc::channel(10);
+        let (tx, mut tr) = mpsc::channel(endpoint.get_channel_capacity());
         let (c_tx, mut c_tr) = mpsc::channel(2);
-        let sender = BrokerSender { sender: tx };
+        let sender = BrokerSender::new(tx);
         let subscription_map = Arc::new(RwLock::new(request.sub_map.clone()));
         let broker = Self {
             sender,
@@ -64,8 +64,16 @@ impl ThunderBroker {
         let callback_for_sender = callback.clone();
         let broker_for_reconnect = broker.clone();
         tokio::spawn(async move {
-            let (mut ws_tx, mut ws_rx) =
-                BrokerUtils::get_ws_broker(&endpoint.get_url(), None).await;
+            let (mut ws_tx, mut ws_rx) = match BrokerUtils::get_ws_broker(&endpoint, None).await {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("Thunder broker couldnt start {:?}", e);
+                    return;
+                }
+            };
+            if let Some(status) = &request.status_callback {
+                status.send_state(BrokerConnectionState::Connected).await;
+            }
 
             // send the first request to the broker. This is the controller statechange subscription request
             let status_request = broker_c
@@ -145,6 +153,10 @@ impl ThunderBroker {
                     }
             }
 
+            if let Some(status) = &request.status_callback {
+                status.send_state(BrokerConnectionState::Disconnected).await;
+            }
+
             let mut reconnect_request = request.clone();
             // Thunder Disconnected try reconnecting.
             {
@@ -345,7 +357,7 @@ mod tests {
     };
     use ripple_sdk::api::gateway::rpc_gateway_api::RpcRequest;
     use serde_json::json;
-    use std::time::Duration;
+    use std::{collections::HashMap, time::Duration};
     use tokio::sync::mpsc;
 
     async fn get_thunderbroker(
@@ -361,6 +373,12 @@ mod tests {
             url: format!("ws://127.0.0.1:{}", port),
             protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
             jsonrpc: false,
+            http_jsonrpc: false,
+            timeout_ms: None,
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            channel_capacity: None,
+            required: false,
         };
         let (tx, _) = mpsc::channel(1);
         let request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
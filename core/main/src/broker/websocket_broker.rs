@@ -17,19 +17,27 @@
 
 use crate::broker::broker_utils::BrokerUtils;
 
-use super::endpoint_broker::{
-    BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerOutputForwarder, BrokerRequest,
-    BrokerSender, EndpointBroker,
+use super::{
+    endpoint_broker::{
+        BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerConnectionState,
+        BrokerOutputForwarder, BrokerRequest, BrokerSender, BrokerStatusCallback, EndpointBroker,
+    },
+    rules_engine::RuleEndpoint,
 };
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{Sink, SinkExt, StreamExt};
 use ripple_sdk::{
     log::{debug, error},
     tokio::{self, sync::mpsc},
 };
 use std::{
     collections::HashMap,
+    fmt::Debug,
     sync::{Arc, RwLock},
 };
+use tokio_tungstenite::tungstenite::Message;
+
+/// Upper bound of requests written to the websocket before a flush, so reads are not starved
+const MAX_COALESCED_WRITES: usize = 64;
 
 pub struct WebsocketBroker {
     sender: BrokerSender,
@@ -39,47 +47,74 @@ pub struct WebsocketBroker {
 impl WebsocketBroker {
     fn start(request: BrokerConnectRequest, callback: BrokerCallback) -> Self {
         let endpoint = request.endpoint.clone();
-        let (tx, mut tr) = mpsc::channel(10);
+        let (tx, mut tr) = mpsc::channel(endpoint.get_channel_capacity());
         let (cleaner_tx, mut cleaner_tr) = mpsc::channel::<String>(1);
         let non_json_rpc_map: Arc<RwLock<HashMap<String, Vec<mpsc::Sender<String>>>>> =
             Arc::new(RwLock::new(HashMap::new()));
         let map_clone = non_json_rpc_map.clone();
-        let broker = BrokerSender { sender: tx };
+        let broker = BrokerSender::with_backpressure(tx);
+        let status = request.status_callback.clone();
         tokio::spawn(async move {
             if endpoint.jsonrpc {
-                let (mut ws_tx, mut ws_rx) =
-                    BrokerUtils::get_ws_broker(&endpoint.get_url(), None).await;
-
-                tokio::pin! {
-                    let read = ws_rx.next();
-                }
+                let mut reconnecting = false;
                 loop {
-                    tokio::select! {
-                        Some(value) = &mut read => {
-                            match value {
-                                Ok(v) => {
-                                    if let tokio_tungstenite::tungstenite::Message::Text(t) = v {
+                    if reconnecting {
+                        Self::send_state(&status, BrokerConnectionState::Reconnecting).await;
+                    }
+                    let (mut ws_tx, mut ws_rx) =
+                        match BrokerUtils::get_ws_broker(&endpoint, None).await {
+                            Ok(v) => v,
+                            Err(e) => {
+                                error!("Websocket broker couldnt start {:?}", e);
+                                return false;
+                            }
+                        };
+                    Self::send_state(&status, BrokerConnectionState::Connected).await;
+
+                    tokio::pin! {
+                        let read = ws_rx.next();
+                    }
+                    loop {
+                        tokio::select! {
+                            value = &mut read => {
+                                match value {
+                                    Some(Ok(Message::Text(t))) => {
                                         // send the incoming text without context back to the sender
                                         Self::handle_jsonrpc_response(t.as_bytes(),callback.clone())
+                                    },
+                                    Some(Ok(Message::Close(_))) | None => {
+                                        error!("Broker Websocket closed by endpoint");
+                                        break
+                                    },
+                                    Some(Ok(_)) => {},
+                                    Some(Err(e)) => {
+                                        error!("Broker Websocket error on read {:?}", e);
+                                        break
                                     }
-                                },
-                                Err(e) => {
-                                    error!("Broker Websocket error on read {:?}", e);
-                                    break false
                                 }
-                            }
 
-                        },
-                        Some(request) = tr.recv() => {
-                            debug!("Got request from receiver for broker {:?}", request);
-                            if let Ok(updated_request) = Self::update_request(&request) {
-                                debug!("Sending request to broker {}", updated_request);
-                                let _feed = ws_tx.feed(tokio_tungstenite::tungstenite::Message::Text(updated_request)).await;
-                                let _flush = ws_tx.flush().await;
+                            },
+                            request = tr.recv() => {
+                                match request {
+                                    Some(request) => {
+                                        Self::send_coalesced(&mut ws_tx, request, &mut tr).await
+                                    }
+                                    None => {
+                                        debug!("Broker request channel closed, closing websocket");
+                                        let _feed = ws_tx.feed(Message::Close(None)).await;
+                                        let _flush = ws_tx.flush().await;
+                                        break
+                                    }
+                                }
                             }
-
                         }
                     }
+                    Self::send_state(&status, BrokerConnectionState::Disconnected).await;
+                    // Nothing can be sent once the broker is dropped so dont reconnect
+                    if tr.is_closed() {
+                        return true;
+                    }
+                    reconnecting = true;
                 }
             } else {
                 let cleaner_clone = non_json_rpc_map.clone();
@@ -101,11 +136,8 @@ impl WebsocketBroker {
 
                 while let Some(v) = tr.recv().await {
                     let id = v.get_id();
-                    let cleaner = WSNotificationBroker::start(
-                        v.clone(),
-                        callback.clone(),
-                        endpoint.get_url().clone(),
-                    );
+                    let cleaner =
+                        WSNotificationBroker::start(v.clone(), callback.clone(), endpoint.clone());
                     {
                         let mut map = map_clone.write().unwrap();
                         let mut sender_list = map.remove(&id).unwrap_or_default();
@@ -125,6 +157,44 @@ impl WebsocketBroker {
             },
         }
     }
+
+    async fn send_state(status: &Option<BrokerStatusCallback>, state: BrokerConnectionState) {
+        if let Some(status) = status {
+            status.send_state(state).await;
+        }
+    }
+
+    /// Writes the given request along with the requests already waiting in the receiver
+    /// and flushes the websocket once for the whole batch.
+    async fn send_coalesced<S>(
+        ws_tx: &mut S,
+        request: BrokerRequest,
+        tr: &mut mpsc::Receiver<BrokerRequest>,
+    ) where
+        S: Sink<Message> + Unpin,
+        S::Error: Debug,
+    {
+        let mut next = Some(request);
+        let mut written = 0;
+        while let Some(request) = next {
+            debug!("Got request from receiver for broker {:?}", request);
+            if let Ok(updated_request) = Self::update_request(&request) {
+                debug!("Sending request to broker {}", updated_request);
+                if let Err(e) = ws_tx.feed(Message::Text(updated_request)).await {
+                    error!("Broker Websocket error on write {:?}", e);
+                }
+            }
+            written += 1;
+            next = if written < MAX_COALESCED_WRITES {
+                tr.try_recv().ok()
+            } else {
+                None
+            };
+        }
+        if let Err(e) = ws_tx.flush().await {
+            error!("Broker Websocket error on flush {:?}", e);
+        }
+    }
 }
 
 pub struct WSNotificationBroker;
@@ -133,14 +203,20 @@ impl WSNotificationBroker {
     fn start(
         request_c: BrokerRequest,
         callback_c: BrokerCallback,
-        url: String,
+        endpoint: RuleEndpoint,
     ) -> mpsc::Sender<String> {
         let (tx, mut tr) = mpsc::channel::<String>(1);
         tokio::spawn(async move {
             let app_id = request_c.get_id();
             let alias = request_c.rule.alias.clone();
             let (mut ws_tx, mut ws_rx) =
-                BrokerUtils::get_ws_broker(&url, Some(alias.clone())).await;
+                match BrokerUtils::get_ws_broker(&endpoint, Some(alias.clone())).await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!("Notification broker couldnt start for {} {:?}", alias, e);
+                        return;
+                    }
+                };
 
             tokio::pin! {
                 let read = ws_rx.next();
@@ -210,7 +286,11 @@ mod tests {
         utils::test_utils::{MockWebsocket, WSMockData},
     };
     use ripple_sdk::api::gateway::rpc_gateway_api::RpcRequest;
-    use serde_json::json;
+    use serde_json::{json, Value};
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
 
     use super::*;
 
@@ -227,6 +307,12 @@ mod tests {
             url: format!("ws://127.0.0.1:{}", port),
             protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
             jsonrpc: false,
+            http_jsonrpc: false,
+            timeout_ms: None,
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            channel_capacity: None,
+            required: false,
         };
         let (tx, _) = mpsc::channel(1);
         let request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
@@ -300,4 +386,230 @@ mod tests {
         // See if ws is closed
         assert!(tr.recv().await.unwrap())
     }
+
+    #[tokio::test]
+    async fn saturated_channel_reports_backpressure() {
+        // Endpoint which never completes the handshake so queued requests are not drained
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = RuleEndpoint {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
+            jsonrpc: true,
+            http_jsonrpc: false,
+            timeout_ms: None,
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            channel_capacity: Some(2),
+            required: false,
+        };
+        let (tx, _) = mpsc::channel(1);
+        let request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
+        let (sender, _rec) = mpsc::channel(1);
+        let broker = WebsocketBroker::start(request, BrokerCallback { sender });
+
+        let request = BrokerRequest {
+            rpc: RpcRequest::get_new_internal("some_method".to_owned(), None),
+            rule: Rule {
+                alias: "some.method".to_owned(),
+                transform: RuleTransform::default(),
+                endpoint: None,
+                filter: None,
+            },
+            subscription_processed: None,
+        };
+        assert!(broker.sender.send(request.clone()).await.is_ok());
+        assert!(broker.sender.send(request.clone()).await.is_ok());
+        assert!(matches!(
+            broker.sender.send(request).await,
+            Err(ripple_sdk::utils::error::RippleError::BrokerError(_))
+        ));
+    }
+
+    /// Sink which records the written messages and the number of flushes
+    #[derive(Default)]
+    struct CountingSink {
+        messages: Vec<Message>,
+        flushes: usize,
+    }
+
+    impl Sink<Message> for CountingSink {
+        type Error = tokio_tungstenite::tungstenite::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+            self.messages.push(item);
+            Ok(())
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            self.flushes += 1;
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn get_request(call_id: u64) -> BrokerRequest {
+        let mut rpc = RpcRequest::get_new_internal("some_method".to_owned(), None);
+        rpc.ctx.call_id = call_id;
+        BrokerRequest {
+            rpc,
+            rule: Rule {
+                alias: "some.method".to_owned(),
+                transform: RuleTransform::default(),
+                endpoint: None,
+                filter: None,
+            },
+            subscription_processed: None,
+        }
+    }
+
+    fn get_ids(sink: &CountingSink) -> Vec<u64> {
+        sink.messages
+            .iter()
+            .map(|m| {
+                let v: Value = serde_json::from_str(m.to_text().unwrap()).unwrap();
+                v["id"].as_u64().unwrap()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn queued_requests_are_flushed_once() {
+        let (tx, mut tr) = mpsc::channel(10);
+        for id in 2..=5 {
+            tx.send(get_request(id)).await.unwrap();
+        }
+        let mut sink = CountingSink::default();
+        WebsocketBroker::send_coalesced(&mut sink, get_request(1), &mut tr).await;
+        assert_eq!(get_ids(&sink), vec![1, 2, 3, 4, 5]);
+        assert_eq!(sink.flushes, 1);
+    }
+
+    #[tokio::test]
+    async fn single_request_is_flushed() {
+        let (_tx, mut tr) = mpsc::channel(10);
+        let mut sink = CountingSink::default();
+        WebsocketBroker::send_coalesced(&mut sink, get_request(1), &mut tr).await;
+        assert_eq!(get_ids(&sink), vec![1]);
+        assert_eq!(sink.flushes, 1);
+    }
+
+    async fn next_state(
+        status_rx: &mut mpsc::Receiver<crate::broker::endpoint_broker::BrokerStatus>,
+    ) -> BrokerConnectionState {
+        tokio::time::timeout(Duration::from_secs(5), status_rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .state
+    }
+
+    #[tokio::test]
+    async fn connection_state_transitions() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = RuleEndpoint {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
+            jsonrpc: true,
+            http_jsonrpc: false,
+            timeout_ms: None,
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            channel_capacity: None,
+            required: false,
+        };
+        let (tx, _) = mpsc::channel(1);
+        let mut request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
+        let (status_tx, mut status_rx) = mpsc::channel(10);
+        request.status_callback = Some(BrokerStatusCallback {
+            key: "somekey".to_owned(),
+            sender: status_tx,
+        });
+        let (sender, _rec) = mpsc::channel(1);
+        let _broker = WebsocketBroker::start(request, BrokerCallback { sender });
+
+        // endpoint comes up
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(
+            next_state(&mut status_rx).await,
+            BrokerConnectionState::Connected
+        );
+
+        // endpoint goes down
+        ws.close(None).await.unwrap();
+        drop(ws);
+        assert_eq!(
+            next_state(&mut status_rx).await,
+            BrokerConnectionState::Disconnected
+        );
+        assert_eq!(
+            next_state(&mut status_rx).await,
+            BrokerConnectionState::Reconnecting
+        );
+
+        // endpoint comes back
+        let (stream, _) = listener.accept().await.unwrap();
+        let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(
+            next_state(&mut status_rx).await,
+            BrokerConnectionState::Connected
+        );
+    }
+
+    #[tokio::test]
+    async fn dropped_broker_stops_reconnecting() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = RuleEndpoint {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
+            jsonrpc: true,
+            http_jsonrpc: false,
+            timeout_ms: None,
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            channel_capacity: None,
+            required: false,
+        };
+        let (tx, _) = mpsc::channel(1);
+        let mut request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
+        let (status_tx, mut status_rx) = mpsc::channel(10);
+        request.status_callback = Some(BrokerStatusCallback {
+            key: "somekey".to_owned(),
+            sender: status_tx,
+        });
+        let (sender, _rec) = mpsc::channel(1);
+        let broker = WebsocketBroker::start(request, BrokerCallback { sender });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(
+            next_state(&mut status_rx).await,
+            BrokerConnectionState::Connected
+        );
+
+        // endpoint removed
+        drop(broker);
+        assert!(matches!(ws.next().await, Some(Ok(Message::Close(_)))));
+        assert_eq!(
+            next_state(&mut status_rx).await,
+            BrokerConnectionState::Disconnected
+        );
+        // the broker task ended without reconnecting
+        assert!(
+            tokio::time::timeout(Duration::from_secs(5), status_rx.recv())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
 }
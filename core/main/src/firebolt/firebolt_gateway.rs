@@ -42,8 +42,10 @@ use crate::{
         telemetry_builder::TelemetryBuilder,
     },
     state::{
-        bootstrap_state::BootstrapState, openrpc_state::OpenRpcState,
-        platform_state::PlatformState, session_state::Session,
+        bootstrap_state::BootstrapState,
+        openrpc_state::{OpenRpcState, SchemaError},
+        platform_state::PlatformState,
+        session_state::Session,
     },
     utils::router_utils::{capture_stage, get_rpc_header_with_status},
 };
@@ -193,20 +195,19 @@ impl FireboltGateway {
             request_c.method.clone(),
             request_c.ctx.app_id.clone(),
         );
+        let features = platform_state.get_device_manifest().get_features();
         let fail_open = matches!(
-            platform_state
-                .get_device_manifest()
-                .get_features()
-                .intent_validation,
+            features.intent_validation,
             ripple_sdk::api::manifest::device_manifest::IntentValidation::FailOpen
         );
+        let strict_params_validation = features.strict_params_validation;
 
         let open_rpc_state = self.state.platform_state.open_rpc_state.clone();
 
         tokio::spawn(async move {
             capture_stage(&mut request_c, "context_ready");
             // Validate incoming request parameters.
-            if let Err(error_string) = validate_request(open_rpc_state, &request_c, fail_open) {
+            if let Err(errors) = validate_request(open_rpc_state, &request_c, fail_open) {
                 TelemetryBuilder::stop_and_send_firebolt_metrics_timer(
                     &platform_state.clone(),
                     metrics_timer,
@@ -214,11 +215,7 @@ impl FireboltGateway {
                 )
                 .await;
 
-                let json_rpc_error = JsonRpcError {
-                    code: JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
-                    message: error_string,
-                    data: None,
-                };
+                let json_rpc_error = invalid_params_error(errors, strict_params_validation);
 
                 send_json_rpc_error(&platform_state, &request, json_rpc_error).await;
                 return;
@@ -307,7 +304,7 @@ fn validate_request(
     open_rpc_state: OpenRpcState,
     request: &RpcRequest,
     fail_open: bool,
-) -> Result<(), String> {
+) -> Result<(), Vec<SchemaError>> {
     // Existing fail open configuration should work where the
     // call should be delegated to the actual handler
     if fail_open {
@@ -335,9 +332,9 @@ fn validate_request(
         if v.is_ok() {
             // Params are valid
             return Ok(());
-        } else if let Err(Some(s)) = v {
+        } else if let Err(Some(errors)) = v {
             // Params are not valid
-            return Err(s);
+            return Err(errors);
         }
         let major_version = open_rpc_state.get_version().major.to_string();
         let openrpc_validator = open_rpc_state.get_openrpc_validator();
@@ -348,13 +345,7 @@ fn validate_request(
                 .params_validator(major_version, &rpc_method.name)
                 .unwrap();
             // validate
-            if let Err(errors) = validator.validate(param) {
-                let mut error_string = String::new();
-                for error in errors {
-                    error_string.push_str(&format!("{} ", error));
-                }
-                return Err(error_string);
-            }
+            SchemaError::collect(&validator, param)?;
             // store validator in runtime for future validations of the same api
             open_rpc_state.add_json_schema_cache(method_name, validator);
         } else {
@@ -370,6 +361,27 @@ fn validate_request(
     Ok(())
 }
 
+/// Strict validation answers with the standard `Invalid params` message and lists the failing
+/// paths in `data`, otherwise the schema errors are joined into the message.
+fn invalid_params_error(errors: Vec<SchemaError>, strict: bool) -> JsonRpcError {
+    if strict {
+        JsonRpcError {
+            code: JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+            message: "Invalid params".to_owned(),
+            data: Some(serde_json::json!(errors)),
+        }
+    } else {
+        JsonRpcError {
+            code: JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+            message: errors
+                .iter()
+                .map(|error| format!("{} ", error.message))
+                .collect(),
+            data: None,
+        }
+    }
+}
+
 async fn send_json_rpc_error(
     platform_state: &PlatformState,
     request: &RpcRequest,
@@ -427,3 +439,48 @@ async fn send_json_rpc_error(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::api::{
+        gateway::rpc_gateway_api::CallContext, manifest::extn_manifest::default_providers,
+    };
+    use ripple_sdk::serde_json::json;
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    fn secure_storage_get(params: Value) -> RpcRequest {
+        RpcRequest::new(
+            "securestorage.get".to_owned(),
+            RpcRequest::prepend_ctx(Some(params), &CallContext::mock()),
+            CallContext::mock(),
+        )
+    }
+
+    #[test]
+    fn test_conforming_params_pass_validation() {
+        let state = OpenRpcState::new(None, Vec::new(), default_providers());
+        let request = secure_storage_get(json!({"scope": "account", "key": "a"}));
+        assert!(validate_request(state.clone(), &request, false).is_ok());
+        // The second call is answered from the schema cache
+        assert!(validate_request(state, &request, false).is_ok());
+    }
+
+    #[test]
+    fn test_non_conforming_params_report_path() {
+        let state = OpenRpcState::new(None, Vec::new(), default_providers());
+        let request = secure_storage_get(json!({"scope": "account", "key": 5}));
+        let errors = validate_request(state, &request, false).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/key");
+
+        let error = invalid_params_error(errors.clone(), true);
+        assert_eq!(error.code, JSON_RPC_STANDARD_ERROR_INVALID_PARAMS);
+        assert_eq!(error.message, "Invalid params");
+        assert_eq!(error.data.unwrap()[0]["path"], json!("/key"));
+
+        let error = invalid_params_error(errors, false);
+        assert_eq!(error.code, JSON_RPC_STANDARD_ERROR_INVALID_PARAMS);
+        assert!(error.data.is_none());
+    }
+}
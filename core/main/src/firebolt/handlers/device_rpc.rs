@@ -35,7 +35,7 @@ use ripple_sdk::{
         device::{
             device_events::{
                 DeviceEvent, DeviceEventCallback, DeviceEventRequest, AUDIO_CHANGED_EVENT,
-                HDCP_CHANGED_EVENT, HDR_CHANGED_EVENT, NETWORK_CHANGED_EVENT,
+                HDCP_CHANGED_EVENT, HDR_CHANGED_EVENT, NETWORK_CHANGED_EVENT, POWER_STATE_CHANGED,
                 SCREEN_RESOLUTION_CHANGED_EVENT, VIDEO_RESOLUTION_CHANGED_EVENT,
             },
             device_info_request::{DeviceInfoRequest, DeviceResponse, FirmwareInfo},
@@ -160,6 +160,12 @@ pub trait Device {
         ctx: CallContext,
         request: ListenRequest,
     ) -> RpcResult<ListenerResponse>;
+    #[method(name = "device.onPowerStateChanged")]
+    async fn on_power_state_changed(
+        &self,
+        ctx: CallContext,
+        request: ListenRequest,
+    ) -> RpcResult<ListenerResponse>;
     #[method(name = "device.provision")]
     async fn provision(
         &self,
@@ -683,6 +689,20 @@ impl DeviceServer for DeviceImpl {
         })
     }
 
+    async fn on_power_state_changed(
+        &self,
+        ctx: CallContext,
+        request: ListenRequest,
+    ) -> RpcResult<ListenerResponse> {
+        // Main already follows power state through the ripple context, no device subscription
+        let listen = request.listen;
+        AppEvents::add_listener(&self.state, POWER_STATE_CHANGED.to_string(), ctx, request);
+        Ok(ListenerResponse {
+            listening: listen,
+            event: POWER_STATE_CHANGED.to_string(),
+        })
+    }
+
     async fn provision(
         &self,
         _ctx: CallContext,
@@ -629,7 +629,8 @@ impl DiscoveryServer for DiscoveryImpl {
             ctx,
             request,
         )
-        .await;
+        .await
+        .map_err(|e| rpc_err(e.to_string()))?;
         Ok(ListenerResponse {
             listening,
             event: ENTITY_INFO_EVENT.to_string(),
@@ -683,7 +684,9 @@ impl DiscoveryServer for DiscoveryImpl {
             correlation_id: entity_info.correlation_id,
             result: ProviderResponsePayload::EntityInfoResponse(entity_info.result),
         };
-        ProviderBroker::provider_response(&self.state, response).await;
+        ProviderBroker::provider_response(&self.state, response)
+            .await
+            .map_err(|_| rpc_err(ProviderBroker::NO_PENDING_REQUEST))?;
         Ok(true)
     }
 
@@ -701,7 +704,8 @@ impl DiscoveryServer for DiscoveryImpl {
             ctx,
             request,
         )
-        .await;
+        .await
+        .map_err(|e| rpc_err(e.to_string()))?;
 
         Ok(ListenerResponse {
             listening,
@@ -763,7 +767,9 @@ impl DiscoveryServer for DiscoveryImpl {
             correlation_id: entity_info.correlation_id,
             result: ProviderResponsePayload::PurchasedContentResponse(entity_info.result),
         };
-        ProviderBroker::provider_response(&self.state, response).await;
+        ProviderBroker::provider_response(&self.state, response)
+            .await
+            .map_err(|_| rpc_err(ProviderBroker::NO_PENDING_REQUEST))?;
         Ok(true)
     }
 
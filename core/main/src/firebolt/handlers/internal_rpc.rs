@@ -0,0 +1,190 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    RpcModule,
+};
+use ripple_sdk::api::{
+    firebolt::fb_general::{ListenRequest, ListenerResponse},
+    gateway::rpc_gateway_api::CallContext,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    broker::endpoint_broker::BrokerLatencySummary,
+    firebolt::rpc::RippleRPCProvider,
+    processor::exn_status_processor::{extn_status_event, EXTN_STATUS_CHANGED_EVENT},
+    service::apps::app_events::AppEvents,
+    state::platform_state::PlatformState,
+    SEMVER_LIGHTWEIGHT, SHA_SHORT,
+};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BuildVersion {
+    pub semver: String,
+    #[serde(rename = "gitHash")]
+    pub git_hash: String,
+}
+
+#[rpc(server)]
+pub trait Internal {
+    #[method(name = "internal.version")]
+    async fn version(&self, ctx: CallContext) -> RpcResult<BuildVersion>;
+    #[method(name = "internal.brokerLatency")]
+    async fn broker_latency(
+        &self,
+        ctx: CallContext,
+    ) -> RpcResult<HashMap<String, BrokerLatencySummary>>;
+    #[method(name = "internal.onExtnStatusChanged")]
+    async fn on_extn_status_changed(
+        &self,
+        ctx: CallContext,
+        request: ListenRequest,
+    ) -> RpcResult<ListenerResponse>;
+}
+
+pub struct InternalImpl {
+    state: PlatformState,
+}
+
+#[async_trait]
+impl InternalServer for InternalImpl {
+    async fn version(&self, _ctx: CallContext) -> RpcResult<BuildVersion> {
+        Ok(BuildVersion {
+            semver: SEMVER_LIGHTWEIGHT.to_owned(),
+            git_hash: SHA_SHORT.to_owned(),
+        })
+    }
+
+    async fn broker_latency(
+        &self,
+        _ctx: CallContext,
+    ) -> RpcResult<HashMap<String, BrokerLatencySummary>> {
+        Ok(self.state.endpoint_state.get_latency_summary())
+    }
+
+    async fn on_extn_status_changed(
+        &self,
+        ctx: CallContext,
+        request: ListenRequest,
+    ) -> RpcResult<ListenerResponse> {
+        let listen = request.listen;
+        let session_id = ctx.session_id.clone();
+        AppEvents::add_listener(
+            &self.state,
+            EXTN_STATUS_CHANGED_EVENT.to_string(),
+            ctx,
+            request,
+        );
+        if listen {
+            // Extensions which reported before the subscription are replayed to the new listener
+            let listener = AppEvents::get_listeners(
+                &self.state.app_events_state,
+                EXTN_STATUS_CHANGED_EVENT,
+                None,
+            )
+            .into_iter()
+            .find(|x| x.call_ctx.session_id.eq(&session_id));
+            if let Some(listener) = listener {
+                for (extn_id, status) in self.state.get_extn_statuses() {
+                    AppEvents::send_event(
+                        &self.state,
+                        &listener,
+                        &extn_status_event(&extn_id, &status),
+                    )
+                    .await;
+                }
+            }
+        }
+        Ok(ListenerResponse {
+            listening: listen,
+            event: EXTN_STATUS_CHANGED_EVENT.to_string(),
+        })
+    }
+}
+
+pub struct InternalRPCProvider;
+
+impl RippleRPCProvider<InternalImpl> for InternalRPCProvider {
+    fn provide(state: PlatformState) -> RpcModule<InternalImpl> {
+        InternalImpl { state }.into_rpc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::session_state::Session;
+    use ripple_sdk::{
+        api::{apps::EffectiveTransport, status_update::ExtnStatus},
+        extn::extn_id::{ExtnClassId, ExtnId},
+        serde_json::{self, Value},
+        tokio::{self, sync::mpsc},
+    };
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    #[tokio::test]
+    async fn test_version_matches_build() {
+        let internal = InternalImpl {
+            state: PlatformState::mock(),
+        };
+        let version = internal.version(CallContext::mock()).await.unwrap();
+        assert_eq!(version.semver, SEMVER_LIGHTWEIGHT);
+        assert_eq!(version.git_hash, SHA_SHORT);
+    }
+
+    #[tokio::test]
+    async fn test_extn_status_is_replayed_on_subscribe() {
+        let state = PlatformState::mock();
+        let id = ExtnId::new_channel(ExtnClassId::Device, "test".into());
+        state
+            .get_extn_status_map()
+            .write()
+            .unwrap()
+            .insert(id.to_string(), ExtnStatus::Ready);
+        let call_context = CallContext::mock();
+        let (session_tx, mut session_rx) = mpsc::channel(2);
+        state.session_state.add_session(
+            call_context.get_id(),
+            Session::new(
+                call_context.app_id.clone(),
+                Some(session_tx),
+                EffectiveTransport::Websocket,
+            ),
+        );
+
+        let internal = InternalImpl {
+            state: state.clone(),
+        };
+        let response = internal
+            .on_extn_status_changed(call_context, ListenRequest { listen: true })
+            .await
+            .unwrap();
+        assert!(response.listening);
+
+        let event = session_rx.try_recv().unwrap();
+        let event: Value = serde_json::from_str(&event.jsonrpc_msg).unwrap();
+        assert_eq!(
+            event["result"],
+            extn_status_event(&id.to_string(), &ExtnStatus::Ready)
+        );
+        assert!(session_rx.try_recv().is_err());
+    }
+}
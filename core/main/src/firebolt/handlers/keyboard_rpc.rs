@@ -154,7 +154,9 @@ impl KeyboardServer for KeyboardImpl {
         resp: KeyboardProviderResponse,
     ) -> RpcResult<Option<()>> {
         let msg = resp.to_provider_response();
-        ProviderBroker::provider_response(&self.platform_state, msg).await;
+        ProviderBroker::provider_response(&self.platform_state, msg)
+            .await
+            .map_err(|_| Error::Custom(String::from(ProviderBroker::NO_PENDING_REQUEST)))?;
         Ok(None)
     }
 
@@ -179,7 +181,9 @@ impl KeyboardServer for KeyboardImpl {
         resp: KeyboardProviderResponse,
     ) -> RpcResult<Option<()>> {
         let msg = resp.to_provider_response();
-        ProviderBroker::provider_response(&self.platform_state, msg).await;
+        ProviderBroker::provider_response(&self.platform_state, msg)
+            .await
+            .map_err(|_| Error::Custom(String::from(ProviderBroker::NO_PENDING_REQUEST)))?;
         Ok(None)
     }
 
@@ -200,7 +204,9 @@ impl KeyboardServer for KeyboardImpl {
         resp: KeyboardProviderResponse,
     ) -> RpcResult<Option<()>> {
         let msg = resp.to_provider_response();
-        ProviderBroker::provider_response(&self.platform_state, msg).await;
+        ProviderBroker::provider_response(&self.platform_state, msg)
+            .await
+            .map_err(|_| Error::Custom(String::from(ProviderBroker::NO_PENDING_REQUEST)))?;
         Ok(None)
     }
 
@@ -306,7 +312,8 @@ impl KeyboardImpl {
             ctx,
             request,
         )
-        .await;
+        .await
+        .map_err(|e| Error::Custom(e.to_string()))?;
         Ok(ListenerResponse {
             listening: listen,
             event: event_name.into(),
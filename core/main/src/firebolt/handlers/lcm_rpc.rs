@@ -117,7 +117,8 @@ impl LifecycleManagementImpl {
             ctx,
             request,
         )
-        .await;
+        .await
+        .map_err(|e| rpc_err(e.to_string()))?;
 
         Ok(ListenerResponse {
             listening: listen,
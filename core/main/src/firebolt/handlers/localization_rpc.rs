@@ -225,7 +225,8 @@ impl LocalizationImpl {
             ctx,
             request,
         )
-        .await;
+        .await
+        .map_err(|e| rpc_err(e.to_string()))?;
 
         Ok(ListenerResponse {
             listening: listen,
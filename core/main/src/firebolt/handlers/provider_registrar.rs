@@ -269,7 +269,8 @@ impl ProviderRegistrar {
                 call_context,
                 request,
             )
-            .await;
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
 
             Ok(ListenerResponse {
                 listening,
@@ -368,13 +369,17 @@ impl ProviderRegistrar {
                 attributes.error_payload_type.clone(),
                 params_sequence,
             ) {
-                ProviderBroker::provider_response(&context.platform_state, provider_response).await;
+                ProviderBroker::provider_response(&context.platform_state, provider_response)
+                    .await
+                    .map_err(|_| Error::Custom(String::from(ProviderBroker::NO_PENDING_REQUEST)))?;
             }
         } else if let Some(provider_response) = ProviderRegistrar::get_provider_response(
             ProviderResponsePayloadType::GenericError,
             params_sequence,
         ) {
-            ProviderBroker::provider_response(&context.platform_state, provider_response).await;
+            ProviderBroker::provider_response(&context.platform_state, provider_response)
+                .await
+                .map_err(|_| Error::Custom(String::from(ProviderBroker::NO_PENDING_REQUEST)))?;
         } else {
             error!(
                 "callback_error: NO Valid ATTRIBUTES: context.method={}",
@@ -425,6 +430,8 @@ impl ProviderRegistrar {
                     let caller = CallerSession {
                         session_id: Some(call_context.session_id.clone()),
                         app_id: Some(call_context.app_id.clone()),
+                        trace_id: call_context.trace_id.clone(),
+                        gateway_secure: call_context.gateway_secure,
                     };
 
                     let provider_broker_request = ProviderBrokerRequest {
@@ -567,7 +574,9 @@ impl ProviderRegistrar {
         if let Some(provider_response) =
             ProviderRegistrar::get_provider_response(response_payload_type, params_sequence)
         {
-            ProviderBroker::provider_response(&context.platform_state, provider_response).await;
+            ProviderBroker::provider_response(&context.platform_state, provider_response)
+                .await
+                .map_err(|_| Error::Custom(String::from(ProviderBroker::NO_PENDING_REQUEST)))?;
         } else {
             error!(
                 "callback_response: Could not resolve response payload type: context.method={}",
@@ -15,7 +15,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use crate::bootstrap::boot::boot;
+use crate::bootstrap::boot::{boot_until_shutdown, shutdown_signal};
 use ripple_sdk::{
     log::{error, info},
     tokio,
@@ -31,8 +31,27 @@ pub mod state;
 pub mod utils;
 include!(concat!(env!("OUT_DIR"), "/version.rs"));
 
-#[tokio::main(worker_threads = 2)]
-async fn main() {
+const DEFAULT_WORKER_THREADS: usize = 2;
+
+/// Number of tokio worker threads, taken from `RIPPLE_WORKER_THREADS` when it holds a positive
+/// number and [DEFAULT_WORKER_THREADS] otherwise.
+fn worker_threads(value: Option<String>) -> usize {
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|threads| *threads > 0)
+        .unwrap_or(DEFAULT_WORKER_THREADS)
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads(std::env::var("RIPPLE_WORKER_THREADS").ok()))
+        .enable_all()
+        .build()
+        .expect("Failure to build tokio runtime");
+    runtime.block_on(run());
+}
+
+async fn run() {
     // Init logger
     if let Err(e) = init_and_configure_logger(SEMVER_LIGHTWEIGHT, "gateway".into()) {
         println!("{:?} logger init error", e);
@@ -42,7 +61,7 @@ async fn main() {
     let bootstate = BootstrapState::build().expect("Failure to init state for bootstrap");
 
     // bootstrap
-    match boot(bootstate).await {
+    match boot_until_shutdown(bootstate, shutdown_signal()).await {
         Ok(_) => {
             info!("Ripple Exited gracefully!");
             std::process::exit(exitcode::OK);
@@ -53,3 +72,19 @@ async fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_threads() {
+        assert_eq!(worker_threads(None), DEFAULT_WORKER_THREADS);
+        assert_eq!(worker_threads(Some("8".to_owned())), 8);
+        assert_eq!(worker_threads(Some("0".to_owned())), DEFAULT_WORKER_THREADS);
+        assert_eq!(
+            worker_threads(Some("many".to_owned())),
+            DEFAULT_WORKER_THREADS
+        );
+    }
+}
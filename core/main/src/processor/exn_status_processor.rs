@@ -23,25 +23,60 @@ use ripple_sdk::{
             DefaultExtnStreamer, ExtnEventProcessor, ExtnStreamProcessor, ExtnStreamer,
         },
         extn_client_message::ExtnMessage,
+        extn_id::ExtnId,
     },
     log::error,
+    serde_json::{json, Value},
     tokio::sync::{mpsc::Receiver as MReceiver, mpsc::Sender as MSender},
 };
 
-use crate::state::extn_state::ExtnState;
+use crate::{
+    service::apps::app_events::AppEvents,
+    state::{extn_state::ExtnState, platform_state::PlatformState},
+};
+
+pub const EXTN_STATUS_CHANGED_EVENT: &str = "internal.onExtnStatusChanged";
+
+#[derive(Debug, Clone)]
+pub struct ExtnStatusProcessorState {
+    extn_state: ExtnState,
+    platform_state: PlatformState,
+}
+
+impl ExtnStatusProcessorState {
+    /// Records the status of the extension and emits [EXTN_STATUS_CHANGED_EVENT] to the apps
+    /// listening for it.
+    pub async fn update_extn_status(&self, id: ExtnId, status: ExtnStatus) {
+        self.extn_state
+            .update_extn_status(id.clone(), status.clone());
+        AppEvents::emit(
+            &self.platform_state,
+            EXTN_STATUS_CHANGED_EVENT,
+            &extn_status_event(&id.to_string(), &status),
+        )
+        .await;
+    }
+}
+
+pub fn extn_status_event(extn_id: &str, status: &ExtnStatus) -> Value {
+    json!({ "extn_id": extn_id, "status": status })
+}
 
 #[derive(Debug)]
 pub struct ExtnStatusProcessor {
-    state: ExtnState,
+    state: ExtnStatusProcessorState,
     streamer: DefaultExtnStreamer,
 }
 
 /// Event processor used for cases where a certain Extension Capability is required to be ready.
 /// Bootstrap uses the [WaitForStatusReadyEventProcessor] to await during Device Connnection before starting the gateway.
 impl ExtnStatusProcessor {
-    pub fn new(state: ExtnState) -> ExtnStatusProcessor {
+    pub fn new(extn_state: ExtnState, platform_state: PlatformState) -> ExtnStatusProcessor {
         ExtnStatusProcessor {
-            state,
+            state: ExtnStatusProcessorState {
+                extn_state,
+                platform_state,
+            },
             streamer: DefaultExtnStreamer::new(),
         }
     }
@@ -49,7 +84,7 @@ impl ExtnStatusProcessor {
 
 impl ExtnStreamProcessor for ExtnStatusProcessor {
     type VALUE = ExtnStatus;
-    type STATE = ExtnState;
+    type STATE = ExtnStatusProcessorState;
 
     fn get_state(&self) -> Self::STATE {
         self.state.clone()
@@ -72,8 +107,10 @@ impl ExtnEventProcessor for ExtnStatusProcessor {
         extracted_message: Self::VALUE,
     ) -> Option<bool> {
         let id = msg.requestor.clone();
-        state.update_extn_status(id.clone(), extracted_message.clone());
-        if let Some(v) = state.get_extn_status_listener(id.clone()) {
+        state
+            .update_extn_status(id.clone(), extracted_message.clone())
+            .await;
+        if let Some(v) = state.extn_state.get_extn_status_listener(id.clone()) {
             if let Err(e) = v.send(extracted_message.clone()).await {
                 error!("Error while sending status {:?}", e);
             }
@@ -81,3 +118,59 @@ impl ExtnEventProcessor for ExtnStatusProcessor {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{bootstrap_state::ChannelsState, session_state::Session};
+    use ripple_sdk::{
+        api::{
+            apps::EffectiveTransport, firebolt::fb_general::ListenRequest,
+            gateway::rpc_gateway_api::CallContext, manifest::extn_manifest::ExtnManifest,
+        },
+        extn::extn_id::ExtnClassId,
+        serde_json,
+        tokio::{self, sync::mpsc},
+    };
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    #[tokio::test]
+    async fn test_ready_transition_is_emitted() {
+        let platform_state = PlatformState::mock();
+        let extn_state = ExtnState::new(ChannelsState::new(), ExtnManifest::default())
+            .with_status_map(platform_state.get_extn_status_map());
+        let state = ExtnStatusProcessorState {
+            extn_state: extn_state.clone(),
+            platform_state: platform_state.clone(),
+        };
+        let call_context = CallContext::mock();
+        let (session_tx, mut session_rx) = mpsc::channel(2);
+        platform_state.session_state.add_session(
+            call_context.get_id(),
+            Session::new(
+                call_context.app_id.clone(),
+                Some(session_tx),
+                EffectiveTransport::Websocket,
+            ),
+        );
+        AppEvents::add_listener(
+            &platform_state,
+            EXTN_STATUS_CHANGED_EVENT.to_string(),
+            call_context,
+            ListenRequest { listen: true },
+        );
+
+        let id = ExtnId::new_channel(ExtnClassId::Device, "test".into());
+        state
+            .update_extn_status(id.clone(), ExtnStatus::Ready)
+            .await;
+
+        assert!(extn_state.is_extn_ready(id.clone()));
+        let event = session_rx.try_recv().unwrap();
+        let event: Value = serde_json::from_str(&event.jsonrpc_msg).unwrap();
+        assert_eq!(
+            event["result"],
+            extn_status_event(&id.to_string(), &ExtnStatus::Ready)
+        );
+    }
+}
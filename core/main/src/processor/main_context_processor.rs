@@ -24,9 +24,9 @@ use ripple_sdk::{
     api::{
         context::{ActivationStatus, RippleContext, RippleContextUpdateType},
         device::{
+            device_events::POWER_STATE_CHANGED,
             device_info_request::DeviceInfoRequest,
-            device_request::{InternetConnectionStatus, PowerState, SystemPowerState},
-            device_user_grants_data::GrantLifespan,
+            device_request::{InternetConnectionStatus, SystemPowerState},
         },
         distributor::distributor_sync::{SyncAndMonitorModule, SyncAndMonitorRequest},
         firebolt::fb_capabilities::{CapEvent, CapabilityRole, FireboltCap, FireboltPermission},
@@ -43,13 +43,16 @@ use ripple_sdk::{
     log::{debug, error, info},
     tokio::{
         self,
-        sync::{mpsc::Receiver as MReceiver, mpsc::Sender as MSender},
+        sync::{mpsc::Receiver as MReceiver, mpsc::Sender as MSender, Mutex},
     },
 };
 static START_PARTNER_EXCLUSION_SYNC_THREAD: Once = Once::new();
 
 use crate::{
-    service::{apps::apps_updater::AppsUpdater, data_governance::DataGovernance},
+    service::{
+        apps::{app_events::AppEvents, apps_updater::AppsUpdater},
+        data_governance::DataGovernance,
+    },
     state::{cap::cap_state::CapState, metrics_state::MetricsState, platform_state::PlatformState},
 };
 
@@ -57,6 +60,8 @@ use crate::{
 pub struct ContextState {
     current_context: Arc<RwLock<RippleContext>>,
     state: PlatformState,
+    // Serializes session initialization so overlapping token changes dont start the monitors twice
+    session_lock: Arc<Mutex<()>>,
 }
 
 #[derive(Debug)]
@@ -73,6 +78,7 @@ impl MainContextProcessor {
             state: ContextState {
                 current_context: Arc::new(RwLock::new(RippleContext::default())),
                 state,
+                session_lock: Arc::new(Mutex::new(())),
             },
             streamer: DefaultExtnStreamer::new(),
         }
@@ -146,6 +152,27 @@ impl MainContextProcessor {
         available_result.is_ok()
     }
 
+    ///
+    /// Method which gets called when the device is no longer activated e.g. on sign out.
+    /// Token capabilities which were available become unavailable along with the account session.
+    ///
+    async fn handle_account_session_cleared(state: &PlatformState) {
+        if !Self::is_update_token(state) {
+            debug!("token:account already unavailable");
+            return;
+        }
+        state.session_state.clear_account_session();
+        for cap in ["token:account", "token:platform"] {
+            CapState::emit(
+                state,
+                &CapEvent::OnUnavailable,
+                FireboltCap::Short(cap.to_owned()),
+                None,
+            )
+            .await;
+        }
+    }
+
     async fn sync_partner_exclusions(state: &PlatformState) {
         let state_for_exclusion = state.clone();
         START_PARTNER_EXCLUSION_SYNC_THREAD.call_once(|| {
@@ -177,53 +204,55 @@ impl MainContextProcessor {
         if !update_token && !Self::check_account_session_token(state).await {
             error!("Account session still not available");
         } else {
-            if state.supports_cloud_sync() {
-                debug!("Cloud Sync  configured as a required contract so starting.");
-                if state
-                    .get_device_manifest()
-                    .configuration
-                    .features
-                    .privacy_settings_storage_type
-                    == PrivacySettingsStorageType::Sync
-                {
-                    debug!(
-                        "Privacy settings storage type is set as sync so starting cloud monitor"
-                    );
-                    if let Some(account_session) = state.session_state.get_account_session() {
-                        debug!("Successfully got account session");
-                        if !update_token {
-                            let sync_response = state
-                                .get_client()
-                                .send_extn_request(SyncAndMonitorRequest::SyncAndMonitor(
-                                    SyncAndMonitorModule::Privacy,
-                                    account_session.clone(),
-                                ))
-                                .await;
-                            debug!("Received Sync response for privacy: {:?}", sync_response);
-                            let sync_response = state
-                                .get_client()
-                                .send_extn_request(SyncAndMonitorRequest::SyncAndMonitor(
-                                    SyncAndMonitorModule::UserGrants,
-                                    account_session.clone(),
-                                ))
-                                .await;
-                            debug!(
-                                "Received Sync response for user grants: {:?}",
-                                sync_response
-                            );
-                        } else {
-                            debug!("cap already available so just updating the token alone");
-                            let update_token_response = state
-                                .get_client()
-                                .send_extn_request(SyncAndMonitorRequest::UpdateDistributorToken(
-                                    account_session.token.clone(),
-                                ))
-                                .await;
-                            debug!("Cap token:account is already in available state. just updating Token res: {:?}", update_token_response);
-                        }
-                        //sync up partner exclusion data and setup polling thread for refreshing it.
-                        Self::sync_partner_exclusions(state).await;
+            let privacy_settings_storage_type = state
+                .get_device_manifest()
+                .configuration
+                .features
+                .privacy_settings_storage_type;
+            if !state.supports_cloud_sync() {
+                debug!("Cloud Sync not configured as a required contract so not starting.");
+            } else if !Self::is_cloud_monitor_enabled(&privacy_settings_storage_type) {
+                debug!(
+                    "Privacy settings storage type is {:?} and not set as sync so not starting cloud monitor",
+                    privacy_settings_storage_type
+                );
+            } else {
+                debug!("Cloud Sync configured as a required contract so starting.");
+                debug!("Privacy settings storage type is set as sync so starting cloud monitor");
+                if let Some(account_session) = state.session_state.get_account_session() {
+                    debug!("Successfully got account session");
+                    if !update_token {
+                        let sync_response = state
+                            .get_client()
+                            .send_extn_request(SyncAndMonitorRequest::SyncAndMonitor(
+                                SyncAndMonitorModule::Privacy,
+                                account_session.clone(),
+                            ))
+                            .await;
+                        debug!("Received Sync response for privacy: {:?}", sync_response);
+                        let sync_response = state
+                            .get_client()
+                            .send_extn_request(SyncAndMonitorRequest::SyncAndMonitor(
+                                SyncAndMonitorModule::UserGrants,
+                                account_session.clone(),
+                            ))
+                            .await;
+                        debug!(
+                            "Received Sync response for user grants: {:?}",
+                            sync_response
+                        );
+                    } else {
+                        debug!("cap already available so just updating the token alone");
+                        let update_token_response = state
+                            .get_client()
+                            .send_extn_request(SyncAndMonitorRequest::UpdateDistributorToken(
+                                account_session.token.clone(),
+                            ))
+                            .await;
+                        debug!("Cap token:account is already in available state. just updating Token res: {:?}", update_token_response);
                     }
+                    //sync up partner exclusion data and setup polling thread for refreshing it.
+                    Self::sync_partner_exclusions(state).await;
                 }
             }
             if state.supports_app_catalog() {
@@ -234,6 +263,12 @@ impl MainContextProcessor {
         }
     }
 
+    /// Cloud monitoring of privacy settings and user grants is only started when the
+    /// privacy settings are synced with the cloud
+    fn is_cloud_monitor_enabled(storage_type: &PrivacySettingsStorageType) -> bool {
+        matches!(storage_type, PrivacySettingsStorageType::Sync)
+    }
+
     fn handle_internet_connection_change(
         state: &PlatformState,
         internet_state: &Option<InternetConnectionStatus>,
@@ -256,26 +291,35 @@ impl MainContextProcessor {
             }
         }
     }
-    fn handle_power_state(state: &PlatformState, power_state: &Option<SystemPowerState>) {
-        // fn handle_power_state(state: &PlatformState, power_state: &SystemPowerState) {
+    async fn handle_power_state(state: &PlatformState, power_state: &Option<SystemPowerState>) {
         let power_state = match power_state {
             Some(state) => state,
             None => return,
         };
 
-        if matches!(power_state.power_state, PowerState::On)
-            && Self::handle_power_active_cleanup(state)
-        {
-            // if power_state.power_state != PowerState::On && Self::handle_power_active_cleanup(state) {
-            info!("Usergrants updated for Powerstate");
+        let lifespans = state
+            .get_device_manifest()
+            .capabilities
+            .get_grant_lifespans_to_clear(&power_state.power_state);
+        for lifespan in lifespans {
+            if state
+                .cap_state
+                .grant_state
+                .delete_all_entries_for_lifespan(&lifespan)
+            {
+                info!(
+                    "Usergrants with lifespan {:?} cleared for Powerstate {:?}",
+                    lifespan, power_state.power_state
+                );
+            }
         }
-    }
 
-    pub fn handle_power_active_cleanup(state: &PlatformState) -> bool {
-        state
-            .cap_state
-            .grant_state
-            .delete_all_entries_for_lifespan(&GrantLifespan::PowerActive)
+        AppEvents::emit(
+            state,
+            POWER_STATE_CHANGED,
+            &serde_json::json!(power_state.power_state),
+        )
+        .await;
     }
 
     pub fn remove_expired_and_inactive_entries(state: &PlatformState) {
@@ -317,6 +361,7 @@ impl ExtnEventProcessor for MainContextProcessor {
                     if let Some(ActivationStatus::AccountToken(t)) =
                         &extracted_message.activation_status
                     {
+                        let _session_guard = state.session_lock.lock().await;
                         state
                             .state
                             .session_state
@@ -326,6 +371,7 @@ impl ExtnEventProcessor for MainContextProcessor {
                 }
                 RippleContextUpdateType::PowerStateChanged => {
                     Self::handle_power_state(&state.state, &extracted_message.system_power_state)
+                        .await
                 }
                 RippleContextUpdateType::InternetConnectionChanged => {
                     Self::handle_internet_connection_change(
@@ -333,7 +379,19 @@ impl ExtnEventProcessor for MainContextProcessor {
                         &extracted_message.internet_connectivity,
                     )
                 }
-                _ => {}
+                RippleContextUpdateType::ActivationStatusChanged => {
+                    if let Some(ActivationStatus::NotActivated) =
+                        &extracted_message.activation_status
+                    {
+                        Self::handle_account_session_cleared(&state.state).await
+                    }
+                }
+                // These updates have no side effects in main, the context is updated below
+                RippleContextUpdateType::TimeZoneChanged
+                | RippleContextUpdateType::FeaturesChanged
+                | RippleContextUpdateType::MetricsContextChanged => {
+                    debug!("No action for context update {:?}", update)
+                }
             }
             {
                 let mut context = state.current_context.write().unwrap();
@@ -343,3 +401,319 @@ impl ExtnEventProcessor for MainContextProcessor {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::{
+        service::extn::ripple_client::RippleClient,
+        state::{bootstrap_state::ChannelsState, session_state::Session},
+    };
+    use ripple_sdk::{
+        api::{
+            apps::EffectiveTransport,
+            context::RippleContextUpdateRequest,
+            device::{
+                device_request::{AccountToken, PowerState, TimeZone},
+                device_user_grants_data::{GrantEntry, GrantLifespan, GrantStatus},
+            },
+            firebolt::fb_general::ListenRequest,
+            gateway::rpc_gateway_api::CallContext,
+            manifest::{device_manifest::DeviceManifest, extn_manifest::ExtnManifest},
+            session::AccountSession,
+        },
+        create_processor,
+        extn::mock_extension_client::{MockExtnClient, MockExtnRequest},
+        framework::ripple_contract::RippleContract,
+        tokio::sync::mpsc,
+        uuid::Uuid,
+    };
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    create_processor!(MockAccountSession, AccountSessionRequest);
+    create_processor!(MockCloudSync, SyncAndMonitorRequest);
+
+    fn account_session() -> AccountSession {
+        AccountSession {
+            id: "id".to_owned(),
+            token: "token".to_owned(),
+            account_id: "account_id".to_owned(),
+            device_id: "device_id".to_owned(),
+        }
+    }
+
+    /// Platform state with cloud sync as a required contract on a mock extn client. The client
+    /// serves the account session and answers every cloud sync request after forwarding it to
+    /// the returned receiver.
+    fn cloud_sync_state(
+        storage_type: PrivacySettingsStorageType,
+    ) -> (
+        PlatformState,
+        mpsc::UnboundedReceiver<SyncAndMonitorRequest>,
+    ) {
+        let mut client = MockExtnClient::main();
+        let (_, mut session_rx) = MockAccountSession::add(&mut client);
+        let (_, mut sync_rx) = MockCloudSync::add(&mut client);
+        MockExtnClient::start(client.clone());
+
+        let mut session_client = client.clone();
+        tokio::spawn(async move {
+            while let Some(MockExtnRequest::Message(msg, request)) = session_rx.recv().await {
+                if let AccountSessionRequest::Get = request {
+                    MockExtnClient::respond_with_payload(
+                        &mut session_client,
+                        msg,
+                        account_session(),
+                    )
+                    .await;
+                }
+            }
+        });
+        let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+        let mut sync_client = client.clone();
+        tokio::spawn(async move {
+            while let Some(MockExtnRequest::Message(msg, request)) = sync_rx.recv().await {
+                requests_tx.send(request).ok();
+                MockExtnClient::respond_with_payload(&mut sync_client, msg, ExtnResponse::None(()))
+                    .await;
+            }
+        });
+
+        let (_, mut device_manifest) = DeviceManifest::load_from_content(
+            include_str!("../../../../examples/manifest/device-manifest-example.json").to_string(),
+        )
+        .unwrap();
+        device_manifest
+            .configuration
+            .features
+            .privacy_settings_storage_type = storage_type;
+        let mut extn_manifest = ExtnManifest::default();
+        extn_manifest.required_contracts = vec![RippleContract::CloudSync.as_clear_string()];
+        let state = PlatformState::new(
+            extn_manifest,
+            device_manifest,
+            RippleClient::test_client(client),
+            vec![],
+            None,
+        );
+        (state, requests_rx)
+    }
+
+    fn sent_requests(
+        requests: &mut mpsc::UnboundedReceiver<SyncAndMonitorRequest>,
+    ) -> Vec<SyncAndMonitorRequest> {
+        let mut sent = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            sent.push(request);
+        }
+        sent
+    }
+
+    #[tokio::test]
+    async fn test_token_unavailable_after_deactivation() {
+        let processor = MainContextProcessor::new(PlatformState::mock());
+        let state = processor.get_state();
+        state
+            .state
+            .session_state
+            .insert_account_session(AccountSession {
+                id: "id".to_owned(),
+                token: "token".to_owned(),
+                account_id: "account_id".to_owned(),
+                device_id: "device_id".to_owned(),
+            });
+        CapState::emit(
+            &state.state,
+            &CapEvent::OnAvailable,
+            FireboltCap::Short("token:account".to_owned()),
+            None,
+        )
+        .await;
+        assert!(MainContextProcessor::is_update_token(&state.state));
+
+        let mut context = RippleContext::default();
+        context.update(ripple_sdk::api::context::RippleContextUpdateRequest::Activation(false));
+        let msg = context.get_event_message();
+        MainContextProcessor::process_event(state.clone(), msg, context).await;
+
+        assert!(!MainContextProcessor::is_update_token(&state.state));
+        assert!(state.state.session_state.get_account_session().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ignored_update_still_updates_context() {
+        let processor = MainContextProcessor::new(PlatformState::mock());
+        let state = processor.get_state();
+        let mut context = RippleContext::default();
+        context.time_zone = Some(TimeZone {
+            time_zone: "America/New_York".to_owned(),
+            offset: -14400,
+        });
+        context.update_type = Some(RippleContextUpdateType::TimeZoneChanged);
+
+        let msg = context.get_event_message();
+        MainContextProcessor::process_event(state.clone(), msg, context.clone()).await;
+        assert_eq!(
+            state.current_context.read().unwrap().time_zone,
+            context.time_zone
+        );
+    }
+
+    #[tokio::test]
+    async fn test_initialize_session_starts_cloud_monitor_for_sync_storage() {
+        let (state, mut requests) = cloud_sync_state(PrivacySettingsStorageType::Sync);
+        MainContextProcessor::initialize_session(&state).await;
+        assert_eq!(
+            sent_requests(&mut requests),
+            vec![
+                SyncAndMonitorRequest::SyncAndMonitor(
+                    SyncAndMonitorModule::Privacy,
+                    account_session()
+                ),
+                SyncAndMonitorRequest::SyncAndMonitor(
+                    SyncAndMonitorModule::UserGrants,
+                    account_session()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_initialize_session_skips_cloud_monitor_without_sync_storage() {
+        for storage_type in [
+            PrivacySettingsStorageType::Local,
+            PrivacySettingsStorageType::Cloud,
+        ] {
+            let (state, mut requests) = cloud_sync_state(storage_type);
+            MainContextProcessor::initialize_session(&state).await;
+            assert!(state.session_state.get_account_session().is_some());
+            assert!(sent_requests(&mut requests).is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_token_changes_start_cloud_monitor_once() {
+        let (platform_state, mut requests) = cloud_sync_state(PrivacySettingsStorageType::Sync);
+        let state = MainContextProcessor::new(platform_state).get_state();
+        let mut context = RippleContext::default();
+        context.update(RippleContextUpdateRequest::Token(AccountToken {
+            token: "token".to_owned(),
+            expires: 0,
+        }));
+        let msg = context.get_event_message();
+
+        tokio::join!(
+            MainContextProcessor::process_event(state.clone(), msg.clone(), context.clone()),
+            MainContextProcessor::process_event(state, msg, context)
+        );
+
+        // The second token change waits for the first session to initialize and only
+        // updates the distributor token
+        let sent = sent_requests(&mut requests);
+        let sync_requests = sent
+            .iter()
+            .filter(|r| matches!(r, SyncAndMonitorRequest::SyncAndMonitor(..)))
+            .count();
+        let update_requests = sent
+            .iter()
+            .filter(|r| matches!(r, SyncAndMonitorRequest::UpdateDistributorToken(_)))
+            .count();
+        assert_eq!(sync_requests, 2);
+        assert_eq!(update_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_power_state_change_is_emitted_to_apps() {
+        let processor = MainContextProcessor::new(PlatformState::mock());
+        let state = processor.get_state();
+        let call_context = CallContext::mock();
+        let (session_tx, mut session_rx) = tokio::sync::mpsc::channel(2);
+        state.state.session_state.add_session(
+            call_context.get_id(),
+            Session::new(
+                call_context.app_id.clone(),
+                Some(session_tx),
+                EffectiveTransport::Websocket,
+            ),
+        );
+        AppEvents::add_listener(
+            &state.state,
+            POWER_STATE_CHANGED.to_owned(),
+            call_context,
+            ListenRequest { listen: true },
+        );
+
+        let mut context = RippleContext::default();
+        context.update(RippleContextUpdateRequest::PowerState(SystemPowerState {
+            power_state: PowerState::On,
+            current_power_state: PowerState::Standby,
+        }));
+        let msg = context.get_event_message();
+        MainContextProcessor::process_event(state, msg, context).await;
+
+        let message = session_rx.try_recv().unwrap();
+        let event: serde_json::Value = serde_json::from_str(&message.jsonrpc_msg).unwrap();
+        assert_eq!(event["result"], serde_json::json!("ON"));
+    }
+
+    fn allowed_grant(capability: &str, lifespan: GrantLifespan) -> GrantEntry {
+        let mut entry = GrantEntry::get(CapabilityRole::Use, capability.to_owned());
+        entry.status = Some(GrantStatus::Allowed);
+        entry.lifespan = Some(lifespan);
+        entry
+    }
+
+    #[tokio::test]
+    async fn test_power_state_clears_configured_grant_lifespans() {
+        let (_, mut manifest) = DeviceManifest::load_from_content(
+            include_str!("../../../../examples/manifest/device-manifest-example.json").to_string(),
+        )
+        .unwrap();
+        manifest.capabilities.power_state_grant_cleanup = Some(HashMap::from([(
+            PowerState::Standby,
+            vec![GrantLifespan::AppActive],
+        )]));
+        let state = PlatformState::new(
+            ExtnManifest::default(),
+            manifest,
+            RippleClient::new(ChannelsState::new()),
+            vec![],
+            None,
+        );
+        // grants are persisted, so use an app which has none yet
+        let app_id = Uuid::new_v4().to_string();
+        let grant_state = &state.cap_state.grant_state;
+        grant_state.update_grant_entry(
+            Some(app_id.clone()),
+            allowed_grant(
+                "xrn:firebolt:capability:test:power",
+                GrantLifespan::PowerActive,
+            ),
+        );
+        grant_state.update_grant_entry(
+            Some(app_id.clone()),
+            allowed_grant("xrn:firebolt:capability:test:app", GrantLifespan::AppActive),
+        );
+
+        MainContextProcessor::handle_power_state(
+            &state,
+            &Some(SystemPowerState {
+                power_state: PowerState::Standby,
+                current_power_state: PowerState::On,
+            }),
+        )
+        .await;
+
+        let remaining: Vec<String> = grant_state
+            .get_grant_entries_for_app_id(app_id)
+            .into_iter()
+            .map(|entry| entry.capability)
+            .collect();
+        assert_eq!(
+            remaining,
+            vec!["xrn:firebolt:capability:test:power".to_owned()]
+        );
+    }
+}
@@ -24,17 +24,20 @@ use ripple_sdk::{
         apps::{AppEventRequest, EffectiveTransport},
         firebolt::fb_general::ListenRequest,
         gateway::rpc_gateway_api::{ApiMessage, CallContext},
+        manifest::device_manifest::{EventOverflowPolicy, EventQueueConfiguration},
         protocol::BridgeProtocolRequest,
     },
-    log::error,
+    log::{error, warn},
     serde_json::{json, Value},
-    tokio::sync::mpsc,
-    utils::channel_utils::mpsc_send_and_log,
+    tokio::{
+        self,
+        sync::mpsc::{self, error::TrySendError},
+    },
 };
 
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, RwLock},
 };
 
 use crate::state::platform_state::PlatformState;
@@ -79,6 +82,14 @@ type ListenersMap = Arc<RwLock<HashMap<String, HashMap<Option<String>, Vec<Event
 #[derive(Clone, Default)]
 pub struct AppEventsState {
     pub listeners: ListenersMap,
+    pub event_queue: EventQueueConfiguration,
+}
+
+impl AppEventsState {
+    pub fn with_event_queue(mut self, event_queue: EventQueueConfiguration) -> Self {
+        self.event_queue = event_queue;
+        self
+    }
 }
 
 impl std::fmt::Debug for AppEventsState {
@@ -122,11 +133,99 @@ impl std::fmt::Debug for AppEventsState {
     }
 }
 
+/// Events on their way to a listener's session. Events go straight into the session channel
+/// while it has room. Once it is full they wait here and a forwarder task feeds them to the
+/// session in order, so a slow session never holds up the emit to other listeners.
+#[derive(Clone)]
+struct EventQueue {
+    session_tx: mpsc::Sender<ApiMessage>,
+    pending: Arc<Mutex<PendingEvents>>,
+    config: EventQueueConfiguration,
+}
+
+#[derive(Default)]
+struct PendingEvents {
+    messages: VecDeque<ApiMessage>,
+    // Set while a forwarder task is delivering, new events have to queue up behind it
+    forwarding: bool,
+}
+
+impl EventQueue {
+    fn new(session_tx: mpsc::Sender<ApiMessage>, config: EventQueueConfiguration) -> Self {
+        EventQueue {
+            session_tx,
+            pending: Arc::new(Mutex::new(PendingEvents::default())),
+            config,
+        }
+    }
+
+    fn push(&self, message: ApiMessage, call_ctx: &CallContext) {
+        let mut pending = self.pending.lock().unwrap();
+        if !pending.forwarding {
+            match self.session_tx.try_send(message) {
+                Ok(_) => {}
+                Err(TrySendError::Full(message)) => {
+                    pending.messages.push_back(message);
+                    pending.forwarding = true;
+                    self.start_forwarder();
+                }
+                Err(TrySendError::Closed(_)) => {
+                    error!(
+                        "session closed, dropping event for app_id={} session_id={}",
+                        call_ctx.app_id, call_ctx.session_id
+                    );
+                }
+            }
+            return;
+        }
+
+        if pending.messages.len() >= self.config.max_pending {
+            warn!(
+                "event queue full for app_id={} session_id={}, applying {:?}",
+                call_ctx.app_id, call_ctx.session_id, self.config.overflow_policy
+            );
+            match self.config.overflow_policy {
+                EventOverflowPolicy::DropOldest => {
+                    pending.messages.pop_front();
+                }
+                EventOverflowPolicy::DropNewest => return,
+            }
+        }
+        pending.messages.push_back(message);
+    }
+
+    fn start_forwarder(&self) {
+        let queue = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let next = {
+                    let mut pending = queue.pending.lock().unwrap();
+                    let next = pending.messages.pop_front();
+                    if next.is_none() {
+                        pending.forwarding = false;
+                    }
+                    next
+                };
+                let Some(message) = next else {
+                    return;
+                };
+                if queue.session_tx.send(message).await.is_err() {
+                    error!("session closed, dropping queued events");
+                    let mut pending = queue.pending.lock().unwrap();
+                    pending.messages.clear();
+                    pending.forwarding = false;
+                    return;
+                }
+            }
+        });
+    }
+}
+
 #[derive(Clone)]
 pub struct EventListener {
     pub call_ctx: CallContext,
-    // Keep the session_tx package private
-    session_tx: Option<mpsc::Sender<ApiMessage>>,
+    // Keep the session queue package private
+    queue: Option<EventQueue>,
     transport: EffectiveTransport,
     decorator: Option<Box<dyn AppEventDecorator + Send + Sync>>,
 }
@@ -241,6 +340,9 @@ impl AppEvents {
             }
         };
         let app_events_state = &state.app_events_state;
+        let queue = session
+            .get_sender()
+            .map(|session_tx| EventQueue::new(session_tx, app_events_state.event_queue.clone()));
         let mut listeners = app_events_state.listeners.write().unwrap();
         let event_ctx_string = event_context.map(|x| x.to_string());
 
@@ -251,7 +353,7 @@ impl AppEvents {
             AppEvents::remove_session_from_events(event_listeners, &call_ctx.session_id);
             event_listeners.push(EventListener {
                 call_ctx,
-                session_tx: session.get_sender(),
+                queue,
                 transport: session.get_transport(),
                 decorator,
             });
@@ -279,8 +381,8 @@ impl AppEvents {
 
         match listener.transport.clone() {
             EffectiveTransport::Websocket => {
-                if let Some(session_tx) = listener.session_tx.clone() {
-                    mpsc_send_and_log(&session_tx, api_message, "GatewayResponse").await;
+                if let Some(queue) = &listener.queue {
+                    queue.push(api_message, &listener.call_ctx);
                 } else {
                     error!("JsonRPC sender missing");
                 }
@@ -344,23 +446,29 @@ impl AppEvents {
         // is present. Otherwise event result without context.
         let listeners = AppEvents::get_listeners(&state.app_events_state, event_name, None);
         for i in listeners {
-            let decorated_res = i.decorate(state, event_name, result).await;
-            if decorated_res.is_err() {
-                error!("could not generate event for '{}'", event_name);
+            if !AppEvents::is_listener_session_active(state, &i) {
                 continue;
             }
+            // A decoration failure only skips the affected listener, the others still get the event
+            let decorated_res = match i.decorate(state, event_name, result).await {
+                Ok(res) => res,
+                Err(_) => {
+                    AppEvents::log_decoration_error(event_name, &i);
+                    continue;
+                }
+            };
             if context.is_some() {
                 AppEvents::send_event(
                     state,
                     &i,
                     &json!({
                         "context": context.clone(),
-                        "value"  : &decorated_res.unwrap(),
+                        "value"  : &decorated_res,
                     }),
                 )
                 .await;
             } else {
-                AppEvents::send_event(state, &i, &decorated_res.unwrap()).await;
+                AppEvents::send_event(state, &i, &decorated_res).await;
             }
         }
 
@@ -373,6 +481,9 @@ impl AppEvents {
                 event_ctx_string.clone(),
             );
             for i in listeners {
+                if !AppEvents::is_listener_session_active(state, &i) {
+                    continue;
+                }
                 AppEvents::send_event(state, &i, result).await;
             }
         }
@@ -387,6 +498,7 @@ impl AppEvents {
         let listeners_vec = AppEvents::get_listeners(&state.app_events_state, event_name, None)
             .into_iter()
             .filter(|listener| listener.call_ctx.app_id.eq(&app_id))
+            .filter(|listener| AppEvents::is_listener_session_active(state, listener))
             .collect::<Vec<_>>();
 
         for i in listeners_vec {
@@ -394,11 +506,18 @@ impl AppEvents {
             if let Ok(res) = decorated_res {
                 AppEvents::send_event(state, &i, &res).await;
             } else {
-                error!("could not generate event for '{}'", event_name);
+                AppEvents::log_decoration_error(event_name, &i);
             }
         }
     }
 
+    fn log_decoration_error(event_name: &str, listener: &EventListener) {
+        error!(
+            "could not generate event for '{}', skipping listener app_id={} session_id={}",
+            event_name, listener.call_ctx.app_id, listener.call_ctx.session_id
+        );
+    }
+
     pub fn is_app_registered_for_event(
         state: &PlatformState,
         app_id: String,
@@ -409,28 +528,26 @@ impl AppEvents {
             .any(|listener| listener.call_ctx.app_id.eq(&app_id));
     }
 
+    /// A listener is only valid for as long as the session it was registered on is open.
+    /// Listeners of a disconnected session are skipped so they are neither decorated nor sent.
+    fn is_listener_session_active(state: &PlatformState, listener: &EventListener) -> bool {
+        state.session_state.has_session(&listener.call_ctx)
+    }
+
     fn remove_session_from_events(event_listeners: &mut Vec<EventListener>, session_id: &String) {
-        let mut itr = event_listeners.iter();
-        let i = itr.position(|x| x.call_ctx.session_id == *session_id);
-        if let Some(index) = i {
-            event_listeners.remove(index);
-        }
+        event_listeners.retain(|x| x.call_ctx.session_id != *session_id);
     }
 
     pub fn remove_session(state: &PlatformState, session_id: String) {
         state.session_state.clear_session(&session_id);
         let mut listeners = state.app_events_state.listeners.write().unwrap();
-        let all_events = listeners.keys().cloned().collect::<Vec<String>>();
-        for event_name in all_events {
-            if let Some(ctx_map) = listeners.get_mut(&event_name) {
-                let all_contexts = ctx_map.keys().cloned().collect::<Vec<Option<String>>>();
-                for context in all_contexts {
-                    if let Some(event_listener) = ctx_map.get_mut(&context) {
-                        AppEvents::remove_session_from_events(event_listener, &session_id);
-                    }
-                }
+        for ctx_map in listeners.values_mut() {
+            for event_listeners in ctx_map.values_mut() {
+                AppEvents::remove_session_from_events(event_listeners, &session_id);
             }
+            ctx_map.retain(|_, event_listeners| !event_listeners.is_empty());
         }
+        listeners.retain(|_, ctx_map| !ctx_map.is_empty());
     }
 }
 #[cfg(test)]
@@ -438,6 +555,7 @@ pub mod tests {
     use crate::state::session_state::Session;
     use ripple_sdk::tokio;
     use ripple_tdk::utils::test_utils::Mockable;
+    use std::time::Duration;
 
     use super::*;
     #[tokio::test]
@@ -478,4 +596,165 @@ pub mod tests {
             AppEvents::get_listeners(&platform_state.app_events_state, "test_event", None);
         assert!(listeners.len() == 1);
     }
+
+    #[tokio::test]
+    pub async fn test_no_emit_after_disconnect() {
+        let platform_state = PlatformState::mock();
+        let call_context = CallContext::mock();
+        let (session_tx, mut session_rx) = mpsc::channel(2);
+        let session = Session::new(
+            call_context.app_id.clone(),
+            Some(session_tx),
+            EffectiveTransport::Websocket,
+        );
+        platform_state
+            .session_state
+            .add_session(call_context.get_id(), session);
+
+        AppEvents::add_listener(
+            &platform_state,
+            "test_event".to_string(),
+            call_context.clone(),
+            ListenRequest { listen: true },
+        );
+        AppEvents::emit(&platform_state, "test_event", &json!(true)).await;
+        assert!(session_rx.try_recv().is_ok());
+
+        // Disconnect tears down the connection and the session
+        platform_state
+            .session_state
+            .clear_session(&call_context.get_id());
+        AppEvents::emit(&platform_state, "test_event", &json!(true)).await;
+        assert!(session_rx.try_recv().is_err());
+
+        AppEvents::remove_session(&platform_state, call_context.session_id.clone());
+        assert!(platform_state
+            .app_events_state
+            .listeners
+            .read()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[derive(Clone)]
+    struct FailingDecorator {}
+
+    #[async_trait]
+    impl AppEventDecorator for FailingDecorator {
+        async fn decorate(
+            &self,
+            _state: &PlatformState,
+            _ctx: &CallContext,
+            _event_name: &str,
+            _val_in: &Value,
+        ) -> Result<Value, AppEventDecorationError> {
+            Err(AppEventDecorationError {})
+        }
+
+        fn dec_clone(&self) -> Box<dyn AppEventDecorator + Send + Sync> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_decorator_error_skips_only_that_listener() {
+        let platform_state = PlatformState::mock();
+        let mut receivers = Vec::new();
+        for (session_id, decorator) in [
+            (
+                "failing",
+                Some(Box::new(FailingDecorator {}) as Box<dyn AppEventDecorator + Send + Sync>),
+            ),
+            ("plain", None),
+        ] {
+            let mut call_context = CallContext::mock();
+            call_context.session_id = session_id.to_owned();
+            call_context.cid = Some(session_id.to_owned());
+            let (session_tx, session_rx) = mpsc::channel(2);
+            let session = Session::new(
+                call_context.app_id.clone(),
+                Some(session_tx),
+                EffectiveTransport::Websocket,
+            );
+            platform_state
+                .session_state
+                .add_session(call_context.get_id(), session);
+            AppEvents::add_listener_with_decorator(
+                &platform_state,
+                "test_event".to_string(),
+                call_context,
+                ListenRequest { listen: true },
+                decorator,
+            );
+            receivers.push(session_rx);
+        }
+
+        AppEvents::emit(&platform_state, "test_event", &json!(true)).await;
+        assert!(receivers[0].try_recv().is_err());
+        assert!(receivers[1].try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    pub async fn test_slow_listener_does_not_stall_others() {
+        let mut platform_state = PlatformState::mock();
+        platform_state.app_events_state =
+            AppEventsState::default().with_event_queue(EventQueueConfiguration {
+                max_pending: 2,
+                overflow_policy: EventOverflowPolicy::DropOldest,
+            });
+        let mut receivers = Vec::new();
+        for (session_id, capacity) in [("slow", 1), ("fast", 1)] {
+            let mut call_context = CallContext::mock();
+            call_context.session_id = session_id.to_owned();
+            call_context.cid = Some(session_id.to_owned());
+            let (session_tx, session_rx) = mpsc::channel(capacity);
+            let session = Session::new(
+                call_context.app_id.clone(),
+                Some(session_tx),
+                EffectiveTransport::Websocket,
+            );
+            platform_state
+                .session_state
+                .add_session(call_context.get_id(), session);
+            AppEvents::add_listener(
+                &platform_state,
+                "test_event".to_string(),
+                call_context,
+                ListenRequest { listen: true },
+            );
+            receivers.push(session_rx);
+        }
+        let mut slow_rx = receivers.remove(0);
+        let mut fast_rx = receivers.remove(0);
+
+        let event_index = |message: ApiMessage| {
+            serde_json::from_str::<Value>(&message.jsonrpc_msg).unwrap()["result"]
+                .as_u64()
+                .unwrap()
+        };
+        for i in 0..10u64 {
+            tokio::time::timeout(
+                Duration::from_secs(1),
+                AppEvents::emit(&platform_state, "test_event", &json!(i)),
+            )
+            .await
+            .expect("emit stalled on the slow listener");
+            let message = tokio::time::timeout(Duration::from_secs(1), fast_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(event_index(message), i);
+        }
+
+        let mut slow_events = Vec::new();
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(100), slow_rx.recv()).await
+        {
+            slow_events.push(event_index(message));
+        }
+        // The first event fit the channel, older queued events were dropped for the last ones
+        assert_eq!(slow_events.first(), Some(&0));
+        assert_eq!(slow_events.last(), Some(&9));
+        assert!(slow_events.len() < 10);
+    }
 }
@@ -19,23 +19,28 @@ use arrayvec::ArrayVec;
 use ripple_sdk::{
     api::{
         firebolt::{
-            fb_capabilities::{CapEvent, FireboltCap},
+            fb_capabilities::{
+                CapEvent, FireboltCap, FireboltCapability, JSON_RPC_SERVER_ERROR_TIMED_OUT,
+            },
             fb_general::ListenRequest,
+            fb_keyboard::KEYBOARD_PROVIDER_CAPABILITY,
             fb_lifecycle_management::{
                 LifecycleManagementEventRequest, LifecycleManagementProviderEvent,
             },
             fb_openrpc::FireboltOpenRpcMethod,
+            fb_pin::PIN_CHALLENGE_CAPABILITY,
             provider::{
-                FocusRequest, ProviderRequest, ProviderRequestPayload, ProviderResponse,
-                ProviderResponsePayload,
+                FocusRequest, GenericProviderError, ProviderRequest, ProviderRequestPayload,
+                ProviderResponse, ProviderResponsePayload, ACK_CHALLENGE_CAPABILITY,
             },
         },
         gateway::rpc_gateway_api::{CallContext, CallerSession},
+        manifest::device_manifest::PROVIDER_INVOKE_TIMEOUT_MS_DEFAULT,
     },
     log::{debug, error, info, warn},
     serde_json,
-    tokio::sync::oneshot,
-    utils::channel_utils::oneshot_send_and_log,
+    tokio::{self, sync::oneshot},
+    utils::{channel_utils::oneshot_send_and_log, error::RippleError},
     uuid::Uuid,
 };
 use serde::{Deserialize, Serialize};
@@ -43,6 +48,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use crate::{
@@ -51,6 +57,12 @@ use crate::{
 };
 
 const REQUEST_QUEUE_CAPACITY: usize = 3;
+/// Providers of these capabilities wait on the user, so their sessions never time out
+const USER_INTERACTION_CAPABILITIES: [&str; 3] = [
+    KEYBOARD_PROVIDER_CAPABILITY,
+    PIN_CHALLENGE_CAPABILITY,
+    ACK_CHALLENGE_CAPABILITY,
+];
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ProviderError {
@@ -58,6 +70,24 @@ pub enum ProviderError {
     NotFound,
     NotSupported,
     IoError,
+    /// The capability method is already provided by the contained app id.
+    AlreadyProvided(String),
+    /// The contained capability is not a valid `xrn:firebolt:capability:<module>:<name>`.
+    MalformedCapability(String),
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderError::AlreadyProvided(app_id) => {
+                write!(f, "Capability is already provided by {}", app_id)
+            }
+            ProviderError::MalformedCapability(capability) => {
+                write!(f, "Malformed capability {}", capability)
+            }
+            _ => write!(f, "{:?}", self),
+        }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -65,6 +95,23 @@ pub struct ProviderBrokerState {
     provider_methods: Arc<RwLock<HashMap<String, ProviderMethod>>>,
     active_sessions: Arc<RwLock<HashMap<String, ProviderSession>>>,
     request_queue: Arc<RwLock<ArrayVec<ProviderBrokerRequest, REQUEST_QUEUE_CAPACITY>>>,
+    invoke_timeout_ms: Option<u64>,
+}
+
+impl ProviderBrokerState {
+    /// Overrides how long a provider session waits for the provider app to respond
+    /// before the caller receives a timeout error.
+    pub fn with_invoke_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.invoke_timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    fn get_invoke_timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.invoke_timeout_ms
+                .unwrap_or(PROVIDER_INVOKE_TIMEOUT_MS_DEFAULT),
+        )
+    }
 }
 
 impl std::fmt::Debug for ProviderBrokerState {
@@ -85,7 +132,7 @@ struct ProviderMethod {
 struct ProviderSession {
     caller: ProviderCaller,
     provider: ProviderMethod,
-    _capability: String,
+    capability: String,
     focused: bool,
 }
 
@@ -118,6 +165,11 @@ impl ProviderResult {
 }
 
 impl ProviderBroker {
+    /// Registers or unregisters `provider` for a capability method.
+    ///
+    /// Registration is first-wins: while one app provides a capability method, registrations
+    /// from any other app are rejected with [ProviderError::AlreadyProvided]. The app which
+    /// owns the registration may register again, replacing its previous session.
     pub async fn register_or_unregister_provider(
         pst: &PlatformState,
         capability: String,
@@ -125,7 +177,17 @@ impl ProviderBroker {
         event_name: String,
         provider: CallContext,
         listen_request: ListenRequest,
-    ) {
+    ) -> Result<(), ProviderError> {
+        let capability = match FireboltCapability::normalize(&capability) {
+            Some(capability) => capability,
+            None => {
+                error!(
+                    "register_or_unregister_provider: malformed capability={} method={}",
+                    capability, method
+                );
+                return Err(ProviderError::MalformedCapability(capability));
+            }
+        };
         if listen_request.listen {
             ProviderBroker::register_provider(
                 pst,
@@ -135,9 +197,10 @@ impl ProviderBroker {
                 provider,
                 listen_request,
             )
-            .await;
+            .await
         } else {
             ProviderBroker::unregister_provider(pst, capability, method, provider).await;
+            Ok(())
         }
     }
 
@@ -168,15 +231,26 @@ impl ProviderBroker {
         event_name: String,
         provider: CallContext,
         listen_request: ListenRequest,
-    ) {
+    ) -> Result<(), ProviderError> {
         debug!(
             "register_provider: capability={}, method={}, event_name={}",
             capability, method, event_name
         );
         let cap_method = format!("{}:{}", capability, method);
-        AppEvents::add_listener(pst, event_name.clone(), provider.clone(), listen_request);
         {
             let mut provider_methods = pst.provider_broker_state.provider_methods.write().unwrap();
+            if let Some(existing) = provider_methods.get(&cap_method) {
+                if existing.provider.app_id != provider.app_id {
+                    warn!(
+                        "register_provider: rejected {} for {}, already provided by {}",
+                        provider.app_id, cap_method, existing.provider.app_id
+                    );
+                    return Err(ProviderError::AlreadyProvided(
+                        existing.provider.app_id.clone(),
+                    ));
+                }
+            }
+            AppEvents::add_listener(pst, event_name.clone(), provider.clone(), listen_request);
             provider_methods.insert(
                 cap_method,
                 ProviderMethod {
@@ -197,7 +271,8 @@ impl ProviderBroker {
             FireboltCap::Full(capability),
             None,
         )
-        .await
+        .await;
+        Ok(())
     }
 
     pub fn get_provider_methods(pst: &PlatformState) -> ProviderResult {
@@ -220,11 +295,31 @@ impl ProviderBroker {
         ProviderResult::new(result)
     }
 
+    /// Lists every registered provider as `(capability, method, app_id)`.
+    pub fn list_providers(pst: &PlatformState) -> Vec<(String, String, String)> {
+        let provider_methods = pst.provider_broker_state.provider_methods.read().unwrap();
+        provider_methods
+            .iter()
+            .filter_map(|(cap_method, provider)| {
+                // capabilities contain ':' themselves so split on the last one
+                cap_method.rsplit_once(':').map(|(capability, method)| {
+                    (
+                        capability.to_owned(),
+                        method.to_owned(),
+                        provider.provider.app_id.clone(),
+                    )
+                })
+            })
+            .collect()
+    }
+
     pub async fn invoke_method(
         pst: &PlatformState,
-        request: ProviderBrokerRequest,
+        mut request: ProviderBrokerRequest,
     ) -> Option<String> {
         let mut provider_app_id = None;
+        // Providers are registered with the normalized capability
+        request.capability = normalize_capability(&request.capability);
 
         let cap_method = format!(
             "{}:{}",
@@ -232,7 +327,10 @@ impl ProviderBroker {
             FireboltOpenRpcMethod::name_with_lowercase_module(&request.method)
         );
 
-        debug!("invoking provider for {}", cap_method);
+        debug!(
+            "invoking provider for {} trace_id={:?}",
+            cap_method, request.caller.trace_id
+        );
 
         let provider_opt = {
             let provider_methods = pst.provider_broker_state.provider_methods.read().unwrap();
@@ -242,9 +340,31 @@ impl ProviderBroker {
         if let Some(provider_method) = provider_opt {
             let event_name = provider_method.event_name.clone();
             let req_params = request.request.clone();
-            let app_id_opt = request.app_id.clone();
+            let app_id_opt = request.app_id.clone().or_else(|| {
+                // A provider UI already in focus for the capability gets the request directly
+                let focused = ProviderBroker::get_focused(pst, &request.capability)?;
+                if focused == provider_method.provider.app_id {
+                    Some(focused)
+                } else {
+                    debug!(
+                        "{} holds focus for {} but {} provides {}",
+                        focused,
+                        request.capability,
+                        provider_method.provider.app_id,
+                        request.method
+                    );
+                    None
+                }
+            });
+            let gateway_secure = request.caller.gateway_secure;
+            let waits_on_user = USER_INTERACTION_CAPABILITIES
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(&request.capability));
             let c_id =
                 ProviderBroker::start_provider_session(pst, request, provider_method.clone());
+            if !waits_on_user {
+                ProviderBroker::expire_session_after_timeout(pst, c_id.clone());
+            }
             if let Some(app_id) = app_id_opt {
                 debug!("Sending request to specific app {}", app_id);
                 AppEvents::emit_to_app(
@@ -254,6 +374,7 @@ impl ProviderBroker {
                     &serde_json::to_value(ProviderRequest {
                         correlation_id: c_id,
                         parameters: req_params,
+                        gateway_secure,
                     })
                     .unwrap(),
                 )
@@ -267,6 +388,7 @@ impl ProviderBroker {
                     &serde_json::to_value(ProviderRequest {
                         correlation_id: c_id,
                         parameters: req_params,
+                        gateway_secure,
                     })
                     .unwrap(),
                 )
@@ -297,13 +419,43 @@ impl ProviderBroker {
                     tx: request.tx,
                 },
                 provider,
-                _capability: request.capability,
+                capability: request.capability,
                 focused: false,
             },
         );
         c_id
     }
 
+    fn expire_session_after_timeout(pst: &PlatformState, c_id: String) {
+        let pst_c = pst.clone();
+        let invoke_timeout = pst.provider_broker_state.get_invoke_timeout();
+        tokio::spawn(async move {
+            tokio::time::sleep(invoke_timeout).await;
+            let session = {
+                let mut active_sessions =
+                    pst_c.provider_broker_state.active_sessions.write().unwrap();
+                active_sessions.remove(&c_id)
+            };
+            if let Some(session) = session {
+                warn!(
+                    "provider session {} timed out after {}ms waiting for {}",
+                    c_id,
+                    invoke_timeout.as_millis(),
+                    session.provider.provider.app_id
+                );
+                oneshot_send_and_log(
+                    session.caller.tx,
+                    ProviderResponsePayload::GenericError(GenericProviderError {
+                        code: JSON_RPC_SERVER_ERROR_TIMED_OUT,
+                        message: "Provider response timeout".into(),
+                        data: None,
+                    }),
+                    "ProviderTimeout",
+                );
+            }
+        });
+    }
+
     fn queue_provider_request(pst: &PlatformState, request: ProviderBrokerRequest) {
         // Remove any duplicate requests.
         ProviderBroker::remove_request(pst, &request.capability);
@@ -316,7 +468,13 @@ impl ProviderBroker {
         request_queue.push(request);
     }
 
-    pub async fn provider_response(pst: &PlatformState, resp: ProviderResponse) {
+    /// Error message for a provider response whose correlation id has no pending request
+    pub const NO_PENDING_REQUEST: &'static str = "No pending request for correlation id";
+
+    pub async fn provider_response(
+        pst: &PlatformState,
+        resp: ProviderResponse,
+    ) -> Result<(), RippleError> {
         debug!(
             "provider_response, {}, {:?}",
             resp.correlation_id, resp.result
@@ -335,9 +493,15 @@ impl ProviderBroker {
                         error!("send event error {:?}", e);
                     }
                 }
+                Ok(())
             }
             None => {
-                error!("Ignored provider response because there was no active session waiting")
+                warn!(
+                    "Ignored provider response for unknown correlation id {}, pending ids={:?}",
+                    resp.correlation_id,
+                    active_sessions.keys().collect::<Vec<&String>>()
+                );
+                Err(RippleError::NotAvailable)
             }
         }
     }
@@ -395,10 +559,11 @@ impl ProviderBroker {
         }
     }
 
-    fn remove_request(pst: &PlatformState, capability: &String) -> Option<ProviderBrokerRequest> {
+    fn remove_request(pst: &PlatformState, capability: &str) -> Option<ProviderBrokerRequest> {
+        let capability = normalize_capability(capability);
         let mut request_queue = pst.provider_broker_state.request_queue.write().unwrap();
         let mut iter = request_queue.iter();
-        let cap = iter.position(|request| request.capability.eq(capability));
+        let cap = iter.position(|request| request.capability.eq(&capability));
         if let Some(index) = cap {
             let request = request_queue.remove(index);
             return Some(request);
@@ -409,12 +574,36 @@ impl ProviderBroker {
     pub async fn focus(
         pst: &PlatformState,
         _ctx: CallContext,
-        _capability: String,
+        capability: String,
         request: FocusRequest,
     ) {
         let mut active_sessions = pst.provider_broker_state.active_sessions.write().unwrap();
+        let focused_capability = match active_sessions.get(&request.correlation_id) {
+            Some(session) => session.capability.clone(),
+            None => {
+                warn!("Focus: No active session for request");
+                return;
+            }
+        };
+        // Only one session holds the focus for a capability
+        let mut previous = None;
+        for (c_id, session) in active_sessions.iter_mut() {
+            if session.focused
+                && session.capability == focused_capability
+                && *c_id != request.correlation_id
+            {
+                session.focused = false;
+                previous = Some(session.provider.provider.app_id.clone());
+            }
+        }
         if let Some(session) = active_sessions.get_mut(&request.correlation_id) {
             session.focused = true;
+            if let Some(previous) = previous.filter(|p| *p != session.provider.provider.app_id) {
+                debug!(
+                    "Focus: {} takes focus for {} from {}",
+                    session.provider.provider.app_id, capability, previous
+                );
+            }
             if pst.has_internal_launcher() {
                 let app_id = session.provider.provider.app_id.clone();
                 let event = LifecycleManagementEventRequest::Provide(
@@ -425,8 +614,497 @@ impl ProviderBroker {
                     error!("send event error {:?}", e);
                 }
             }
-        } else {
-            warn!("Focus: No active session for request");
         }
     }
+
+    /// Returns the app holding focus for an active provider session of `capability`.
+    pub fn get_focused(pst: &PlatformState, capability: &str) -> Option<String> {
+        let capability = normalize_capability(capability);
+        let active_sessions = pst.provider_broker_state.active_sessions.read().unwrap();
+        active_sessions
+            .values()
+            .find(|session| session.focused && session.capability == capability)
+            .map(|session| session.provider.provider.app_id.clone())
+    }
+}
+
+/// Capabilities are stored normalized, so lookups normalize too and match regardless of case.
+/// A malformed capability is kept as is and simply matches no provider.
+fn normalize_capability(capability: &str) -> String {
+    FireboltCapability::normalize(capability).unwrap_or_else(|| capability.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::session_state::Session;
+    use ripple_sdk::{
+        api::{
+            apps::EffectiveTransport,
+            gateway::rpc_gateway_api::{ApiMessage, CallContext},
+        },
+        tokio::sync::mpsc,
+        Mockable,
+    };
+
+    fn provider_request(
+        capability: &str,
+        method: &str,
+    ) -> (
+        ProviderBrokerRequest,
+        oneshot::Receiver<ProviderResponsePayload>,
+    ) {
+        let (tx, rx) = oneshot::channel::<ProviderResponsePayload>();
+        let request = ProviderBrokerRequest {
+            capability: capability.to_owned(),
+            method: method.to_owned(),
+            caller: CallerSession::default(),
+            request: ProviderRequestPayload::Generic(serde_json::Value::Null),
+            tx,
+            app_id: None,
+        };
+        (request, rx)
+    }
+
+    async fn register(pst: &PlatformState, capability: &str, method: &str, app_id: &str) {
+        let mut provider = CallContext::mock();
+        provider.app_id = app_id.to_owned();
+        provider.session_id = format!("{}_session", app_id);
+        ProviderBroker::register_or_unregister_provider(
+            pst,
+            capability.to_owned(),
+            method.to_owned(),
+            format!("{}.onRequest", method),
+            provider,
+            ListenRequest { listen: true },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_provider_times_out() {
+        let mut pst = PlatformState::mock();
+        pst.provider_broker_state = ProviderBrokerState::default().with_invoke_timeout_ms(50);
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:provider",
+            "test.provide",
+            "app1",
+        )
+        .await;
+
+        let (request, rx) =
+            provider_request("xrn:firebolt:capability:test:provider", "test.provide");
+        let app_id = ProviderBroker::invoke_method(&pst, request).await;
+        assert_eq!(app_id, Some("app1".to_owned()));
+
+        let result = tokio::time::timeout(Duration::from_secs(2), rx)
+            .await
+            .expect("caller was never released")
+            .unwrap();
+        match result {
+            ProviderResponsePayload::GenericError(e) => {
+                assert_eq!(e.code, JSON_RPC_SERVER_ERROR_TIMED_OUT)
+            }
+            other => panic!("unexpected response {:?}", other),
+        }
+        assert!(pst
+            .provider_broker_state
+            .active_sessions
+            .read()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_user_interaction_provider_does_not_time_out() {
+        let mut pst = PlatformState::mock();
+        pst.provider_broker_state = ProviderBrokerState::default().with_invoke_timeout_ms(50);
+        register(
+            &pst,
+            KEYBOARD_PROVIDER_CAPABILITY,
+            "keyboard.standard",
+            "app1",
+        )
+        .await;
+
+        let (request, rx) = provider_request(KEYBOARD_PROVIDER_CAPABILITY, "keyboard.standard");
+        assert!(ProviderBroker::invoke_method(&pst, request).await.is_some());
+
+        // the user may take much longer than the timeout to type
+        assert!(tokio::time::timeout(Duration::from_millis(200), rx)
+            .await
+            .is_err());
+        assert_eq!(
+            pst.provider_broker_state
+                .active_sessions
+                .read()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_providers() {
+        let pst = PlatformState::mock();
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:one",
+            "test.provideOne",
+            "app1",
+        )
+        .await;
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:two",
+            "test.provideTwo",
+            "app2",
+        )
+        .await;
+
+        let mut providers = ProviderBroker::list_providers(&pst);
+        providers.sort();
+        assert_eq!(
+            providers,
+            vec![
+                (
+                    "xrn:firebolt:capability:test:one".to_owned(),
+                    "test.provideOne".to_owned(),
+                    "app1".to_owned()
+                ),
+                (
+                    "xrn:firebolt:capability:test:two".to_owned(),
+                    "test.provideTwo".to_owned(),
+                    "app2".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_correlation_id_is_rejected() {
+        let pst = PlatformState::mock();
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:provider",
+            "test.provide",
+            "app1",
+        )
+        .await;
+        let (request, _rx) =
+            provider_request("xrn:firebolt:capability:test:provider", "test.provide");
+        ProviderBroker::invoke_method(&pst, request).await;
+
+        let result = ProviderBroker::provider_response(
+            &pst,
+            ProviderResponse {
+                correlation_id: "unknown".to_owned(),
+                result: ProviderResponsePayload::GenericResponse(serde_json::Value::Null),
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(RippleError::NotAvailable)));
+        assert_eq!(
+            pst.provider_broker_state
+                .active_sessions
+                .read()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_second_provider_is_rejected() {
+        let pst = PlatformState::mock();
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:provider",
+            "test.provide",
+            "app1",
+        )
+        .await;
+
+        let mut provider = CallContext::mock();
+        provider.app_id = "app2".to_owned();
+        let result = ProviderBroker::register_or_unregister_provider(
+            &pst,
+            "xrn:firebolt:capability:test:provider".to_owned(),
+            "test.provide".to_owned(),
+            "test.onRequestProvide".to_owned(),
+            provider,
+            ListenRequest { listen: true },
+        )
+        .await;
+        match result {
+            Err(ProviderError::AlreadyProvided(app_id)) => assert_eq!(app_id, "app1"),
+            other => panic!("unexpected registration result {:?}", other),
+        }
+        let providers = ProviderBroker::list_providers(&pst);
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].2, "app1");
+
+        // the owning app can register again
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:provider",
+            "test.provide",
+            "app1",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_malformed_capability_is_rejected() {
+        let pst = PlatformState::mock();
+        let result = ProviderBroker::register_or_unregister_provider(
+            &pst,
+            "not a capability".to_owned(),
+            "test.provide".to_owned(),
+            "test.onRequestProvide".to_owned(),
+            CallContext::mock(),
+            ListenRequest { listen: true },
+        )
+        .await;
+        assert!(matches!(result, Err(ProviderError::MalformedCapability(_))));
+        assert!(ProviderBroker::list_providers(&pst).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_capability_lookups_ignore_case() {
+        let pst = PlatformState::mock();
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:provider",
+            "test.provide",
+            "app1",
+        )
+        .await;
+
+        let (request, _rx) =
+            provider_request("XRN:Firebolt:Capability:Test:Provider", "test.provide");
+        assert_eq!(
+            ProviderBroker::invoke_method(&pst, request).await,
+            Some("app1".to_owned())
+        );
+
+        let c_id = {
+            let active_sessions = pst.provider_broker_state.active_sessions.read().unwrap();
+            active_sessions.keys().next().cloned().unwrap()
+        };
+        ProviderBroker::focus(
+            &pst,
+            CallContext::mock(),
+            "xrn:firebolt:capability:test:provider".to_owned(),
+            FocusRequest {
+                correlation_id: c_id,
+            },
+        )
+        .await;
+        assert_eq!(
+            ProviderBroker::get_focused(&pst, "XRN:FIREBOLT:CAPABILITY:TEST:PROVIDER"),
+            Some("app1".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_provider_request_carries_gateway_secure() {
+        let pst = PlatformState::mock();
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:provider",
+            "test.provide",
+            "app1",
+        )
+        .await;
+        // stub provider app listening on the session used by `register`
+        let mut provider = CallContext::mock();
+        provider.app_id = "app1".to_owned();
+        provider.session_id = "app1_session".to_owned();
+        let (session_tx, mut session_rx) = mpsc::channel(2);
+        pst.session_state.add_session(
+            provider.get_id(),
+            Session::new(
+                provider.app_id.clone(),
+                Some(session_tx),
+                EffectiveTransport::Websocket,
+            ),
+        );
+
+        for gateway_secure in [true, false] {
+            let mut ctx = CallContext::mock();
+            ctx.gateway_secure = gateway_secure;
+            let (mut request, _rx) =
+                provider_request("xrn:firebolt:capability:test:provider", "test.provide");
+            request.caller = ctx.into();
+            ProviderBroker::invoke_method(&pst, request).await;
+
+            let message = session_rx.try_recv().unwrap();
+            let event: serde_json::Value = serde_json::from_str(&message.jsonrpc_msg).unwrap();
+            assert_eq!(
+                event["result"]["gatewaySecure"],
+                serde_json::json!(gateway_secure)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_focused_per_capability() {
+        let pst = PlatformState::mock();
+        let capabilities = [
+            (
+                "xrn:firebolt:capability:test:one",
+                "test.provideOne",
+                "app1",
+            ),
+            (
+                "xrn:firebolt:capability:test:two",
+                "test.provideTwo",
+                "app2",
+            ),
+        ];
+        for (capability, method, app_id) in capabilities {
+            register(&pst, capability, method, app_id).await;
+            let (request, _rx) = provider_request(capability, method);
+            ProviderBroker::invoke_method(&pst, request).await;
+            assert_eq!(ProviderBroker::get_focused(&pst, capability), None);
+        }
+
+        let correlation_ids = {
+            let active_sessions = pst.provider_broker_state.active_sessions.read().unwrap();
+            active_sessions
+                .iter()
+                .map(|(c_id, session)| (session.capability.clone(), c_id.clone()))
+                .collect::<HashMap<String, String>>()
+        };
+        for (capability, _, _) in capabilities {
+            ProviderBroker::focus(
+                &pst,
+                CallContext::mock(),
+                capability.to_owned(),
+                FocusRequest {
+                    correlation_id: correlation_ids[capability].clone(),
+                },
+            )
+            .await;
+        }
+
+        for (capability, _, app_id) in capabilities {
+            assert_eq!(
+                ProviderBroker::get_focused(&pst, capability),
+                Some(app_id.to_owned())
+            );
+        }
+        assert_eq!(
+            ProviderBroker::get_focused(&pst, "xrn:firebolt:capability:test:three"),
+            None
+        );
+    }
+
+    /// Correlation ids of the active sessions by provider app
+    fn active_correlation_ids(pst: &PlatformState) -> HashMap<String, String> {
+        let active_sessions = pst.provider_broker_state.active_sessions.read().unwrap();
+        active_sessions
+            .iter()
+            .map(|(c_id, session)| (session.provider.provider.app_id.clone(), c_id.clone()))
+            .collect()
+    }
+
+    async fn focus(pst: &PlatformState, correlation_id: &str) {
+        ProviderBroker::focus(
+            pst,
+            CallContext::mock(),
+            "xrn:firebolt:capability:test:provider".to_owned(),
+            FocusRequest {
+                correlation_id: correlation_id.to_owned(),
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_focus_moves_between_apps_of_a_capability() {
+        let pst = PlatformState::mock();
+        for (method, app_id) in [("test.provideOne", "app1"), ("test.provideTwo", "app2")] {
+            register(
+                &pst,
+                "xrn:firebolt:capability:test:provider",
+                method,
+                app_id,
+            )
+            .await;
+            let (request, _rx) = provider_request("xrn:firebolt:capability:test:provider", method);
+            ProviderBroker::invoke_method(&pst, request).await;
+        }
+        let correlation_ids = active_correlation_ids(&pst);
+
+        focus(&pst, &correlation_ids["app1"]).await;
+        focus(&pst, &correlation_ids["app2"]).await;
+
+        assert_eq!(
+            ProviderBroker::get_focused(&pst, "xrn:firebolt:capability:test:provider"),
+            Some("app2".to_owned())
+        );
+        let active_sessions = pst.provider_broker_state.active_sessions.read().unwrap();
+        assert!(!active_sessions[&correlation_ids["app1"]].focused);
+    }
+
+    /// Adds an active session for the app and listens to the event on it
+    fn listen(
+        pst: &PlatformState,
+        app_id: &str,
+        event_name: Option<&str>,
+    ) -> mpsc::Receiver<ApiMessage> {
+        let mut ctx = CallContext::mock();
+        ctx.app_id = app_id.to_owned();
+        ctx.session_id = format!("{}_session", app_id);
+        let (session_tx, session_rx) = mpsc::channel(4);
+        pst.session_state.add_session(
+            ctx.get_id(),
+            Session::new(
+                ctx.app_id.clone(),
+                Some(session_tx),
+                EffectiveTransport::Websocket,
+            ),
+        );
+        if let Some(event_name) = event_name {
+            AppEvents::add_listener(
+                pst,
+                event_name.to_owned(),
+                ctx,
+                ListenRequest { listen: true },
+            );
+        }
+        session_rx
+    }
+
+    #[tokio::test]
+    async fn test_request_is_routed_to_focused_provider() {
+        let pst = PlatformState::mock();
+        register(
+            &pst,
+            "xrn:firebolt:capability:test:provider",
+            "test.provide",
+            "app1",
+        )
+        .await;
+        // app1 listens through `register`, app2 only listens to the event
+        let mut provider_rx = listen(&pst, "app1", None);
+        let mut other_rx = listen(&pst, "app2", Some("test.provide.onRequest"));
+
+        let (request, _rx) =
+            provider_request("xrn:firebolt:capability:test:provider", "test.provide");
+        ProviderBroker::invoke_method(&pst, request).await;
+        assert!(provider_rx.try_recv().is_ok());
+        assert!(other_rx.try_recv().is_ok());
+
+        focus(&pst, &active_correlation_ids(&pst)["app1"]).await;
+        let (request, _rx) =
+            provider_request("xrn:firebolt:capability:test:provider", "test.provide");
+        ProviderBroker::invoke_method(&pst, request).await;
+        assert!(provider_rx.try_recv().is_ok());
+        assert!(other_rx.try_recv().is_err());
+    }
 }
@@ -1258,7 +1258,9 @@ impl GrantPolicyEnforcer {
         // 3. Call the capability,
         // 4. Get the user response and return
 
-        let CallerSession { session_id, app_id } = caller_session;
+        let CallerSession {
+            session_id, app_id, ..
+        } = caller_session;
         if session_id.is_some() && app_id.is_some() {
             // session id is some, so caller is from method invoke
             debug!("Method invoke caller, check if app is in foreground state");
@@ -2024,7 +2026,8 @@ mod tests {
                     ctx_c.clone(),
                     ListenRequest { listen: true },
                 )
-                .await;
+                .await
+                .unwrap();
 
                 ProviderBroker::register_or_unregister_provider(
                     &state_c,
@@ -2034,7 +2037,8 @@ mod tests {
                     ctx_c.clone(),
                     ListenRequest { listen: true },
                 )
-                .await;
+                .await
+                .unwrap();
 
                 let platform_state = state.clone();
 
@@ -2053,7 +2057,7 @@ mod tests {
                                     pin_response.clone(),
                                 ),
                             };
-                            ProviderBroker::provider_response(&platform_state, msg).await;
+                            let _ = ProviderBroker::provider_response(&platform_state, msg).await;
                         } else {
                             let req =
                                 serde_json::from_value::<ExternalProviderRequest<Challenge>>(msg);
@@ -2065,7 +2069,8 @@ mod tests {
                                         ack_response.clone(),
                                     ),
                                 };
-                                ProviderBroker::provider_response(&platform_state, msg).await;
+                                let _ =
+                                    ProviderBroker::provider_response(&platform_state, msg).await;
                             }
                         }
                     }
@@ -122,14 +122,15 @@ impl BootstrapState {
         let device_manifest = LoadDeviceManifestStep::get_manifest();
         let app_manifest_result = LoadAppLibraryStep::load_app_library();
         let extn_manifest = LoadExtnManifestStep::get_manifest();
-        let extn_state = ExtnState::new(channels_state.clone(), extn_manifest.clone());
         let platform_state = PlatformState::new(
-            extn_manifest,
+            extn_manifest.clone(),
             device_manifest,
             client,
             app_manifest_result,
             ripple_version_from_etc(),
         );
+        let extn_state = ExtnState::new(channels_state.clone(), extn_manifest)
+            .with_status_map(platform_state.get_extn_status_map());
 
         fn ripple_version_from_etc() -> Option<String> {
             /*
@@ -18,7 +18,8 @@
 use std::{
     collections::HashMap,
     sync::{Arc, RwLock},
-    thread,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use jsonrpsee::core::server::rpc_module::Methods;
@@ -34,8 +35,8 @@ use ripple_sdk::{
         ffi::{ffi_channel::ExtnChannel, ffi_library::ExtnMetadata, ffi_message::CExtnMessage},
     },
     libloading::Library,
-    log::info,
-    tokio::sync::mpsc,
+    log::{error, info, warn},
+    tokio::{self, sync::mpsc},
     utils::error::RippleError,
 };
 
@@ -103,11 +104,56 @@ impl LoadedLibrary {
 
     pub fn get_symbols(&self) {}
 
+    /// Checks the symbols declared in the manifest entry against the symbols exposed by the
+    /// library. Mismatches are logged, a missing `required` symbol fails the validation.
+    pub fn validate_symbols(&self) -> Result<(), RippleError> {
+        let library_ids: Vec<String> = self
+            .metadata
+            .symbols
+            .iter()
+            .map(|x| x.id.to_string())
+            .collect();
+        validate_symbol_ids(&self.entry.path, &self.entry.symbols, &library_ids)
+    }
+
     pub fn get_metadata(&self) -> Box<ExtnMetadata> {
         self.metadata.clone()
     }
 }
 
+fn validate_symbol_ids(
+    path: &str,
+    manifest_symbols: &[ExtnSymbol],
+    library_ids: &[String],
+) -> Result<(), RippleError> {
+    let mut result = Ok(());
+    for symbol in manifest_symbols {
+        if !library_ids.contains(&symbol.id) {
+            if symbol.required {
+                error!(
+                    "required symbol {} in manifest is not exposed by library {}",
+                    symbol.id, path
+                );
+                result = Err(RippleError::BootstrapError);
+            } else {
+                warn!(
+                    "symbol {} in manifest is not exposed by library {}",
+                    symbol.id, path
+                );
+            }
+        }
+    }
+    for id in library_ids {
+        if !manifest_symbols.iter().any(|x| x.id.eq(id)) {
+            warn!(
+                "symbol {} exposed by library {} is missing in the manifest",
+                id, path
+            );
+        }
+    }
+    result
+}
+
 #[derive(Debug)]
 pub struct PreLoadedExtnChannel {
     pub channel: Box<ExtnChannel>,
@@ -115,6 +161,20 @@ pub struct PreLoadedExtnChannel {
     pub symbol: ExtnSymbol,
 }
 
+/// Handles of a started channel used to stop it
+#[derive(Debug)]
+pub struct StartedExtnChannel {
+    shutdown: CSender<CExtnMessage>,
+    handle: JoinHandle<()>,
+}
+
+/// Time given to a channel to return from its `start` method once it was asked to stop
+const EXTN_STOP_TIMEOUT_MS: u64 = 5000;
+
+/// Last reported [ExtnStatus] of each extension keyed by the extension id, shared with
+/// [crate::state::platform_state::PlatformState] so it can be read after bootstrap.
+pub type ExtnStatusMap = Arc<RwLock<HashMap<String, ExtnStatus>>>;
+
 /// Bootstrap state which is used to store transient extension information used while bootstrapping.
 /// Content within state is related to extension symbols and Libraries.
 #[derive(Debug, Clone)]
@@ -124,9 +184,10 @@ pub struct ExtnState {
     pub loaded_libraries: Arc<RwLock<Vec<LoadedLibrary>>>,
     pub device_channels: Arc<RwLock<Vec<PreLoadedExtnChannel>>>,
     pub deferred_channels: Arc<RwLock<Vec<PreLoadedExtnChannel>>>,
-    extn_status_map: Arc<RwLock<HashMap<String, ExtnStatus>>>,
+    extn_status_map: ExtnStatusMap,
     extn_status_listeners: Arc<RwLock<HashMap<String, mpsc::Sender<ExtnStatus>>>>,
     pub extn_methods: Arc<RwLock<Methods>>,
+    started_channels: Arc<RwLock<HashMap<String, StartedExtnChannel>>>,
 }
 
 impl ExtnState {
@@ -140,9 +201,16 @@ impl ExtnState {
             extn_status_map: Arc::new(RwLock::new(HashMap::new())),
             extn_status_listeners: Arc::new(RwLock::new(HashMap::new())),
             extn_methods: Arc::new(RwLock::new(Methods::new())),
+            started_channels: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
+    /// Uses the given map to track the extension statuses instead of a private one.
+    pub fn with_status_map(mut self, extn_status_map: ExtnStatusMap) -> Self {
+        self.extn_status_map = extn_status_map;
+        self
+    }
+
     pub fn update_extn_status(&self, id: ExtnId, status: ExtnStatus) {
         let mut extn_status_map = self.extn_status_map.write().unwrap();
         let _ = extn_status_map.insert(id.to_string(), status);
@@ -160,6 +228,12 @@ impl ExtnState {
         false
     }
 
+    /// Returns a copy of the status of every extension which reported one, the lock is only
+    /// held for the duration of the copy.
+    pub fn snapshot_statuses(&self) -> HashMap<String, ExtnStatus> {
+        self.extn_status_map.read().unwrap().clone()
+    }
+
     pub fn add_extn_status_listener(&self, id: ExtnId, sender: mpsc::Sender<ExtnStatus>) -> bool {
         {
             if self.is_extn_ready(id.clone()) {
@@ -171,6 +245,52 @@ impl ExtnState {
         false
     }
 
+    /// Waits until the given extension reports [ExtnStatus::Ready].
+    /// Returns [RippleError::TimedOut] if the extension is not ready within the timeout and
+    /// [RippleError::BootstrapError] if the extension reports an error.
+    pub async fn wait_for_ready(&self, id: ExtnId, timeout: Duration) -> Result<(), RippleError> {
+        let (tx, mut tr) = mpsc::channel(1);
+        if self.add_extn_status_listener(id.clone(), tx) {
+            return Ok(());
+        }
+        let result = tokio::time::timeout(timeout, async {
+            while let Some(v) = tr.recv().await {
+                match v {
+                    ExtnStatus::Ready => break,
+                    // When Extension is in this state means it has minimal success criteria for continuing
+                    // yet not fully ready due to some errors.
+                    // Expectation of the system here progressive to wait for an eventual success
+                    // without exiting with the error
+                    ExtnStatus::Interrupted => warn!(
+                        "{} extension is interrupted state. Bootstrap currently paused until extension becomes ready.",
+                        id.to_string()
+                    ),
+                    ExtnStatus::Error => {
+                        error!(
+                            "{} extension failed to load. Ripple needs to be restarted.",
+                            id.to_string()
+                        );
+                        return Err(RippleError::BootstrapError);
+                    }
+                }
+            }
+            Ok(())
+        })
+        .await;
+        match result {
+            Ok(r) => r,
+            Err(_) => {
+                error!(
+                    "{} extension did not become ready within {:?}",
+                    id.to_string(),
+                    timeout
+                );
+                self.clear_status_listener(id);
+                Err(RippleError::TimedOut)
+            }
+        }
+    }
+
     pub fn get_extn_status_listener(&self, id: ExtnId) -> Option<mpsc::Sender<ExtnStatus>> {
         let extn_status_listeners = self.extn_status_listeners.read().unwrap();
         extn_status_listeners.get(id.to_string().as_str()).cloned()
@@ -202,13 +322,98 @@ impl ExtnState {
         );
         let (extn_tx, extn_rx) = ChannelsState::get_iec_channel();
         let extn_channel = channel.channel;
-        thread::spawn(move || {
+        let handle = thread::spawn(move || {
             (extn_channel.start)(extn_sender, extn_rx);
         });
+        {
+            let mut started_channels = self.started_channels.write().unwrap();
+            let _ = started_channels.insert(
+                extn_id.to_string(),
+                StartedExtnChannel {
+                    shutdown: extn_tx.clone(),
+                    handle,
+                },
+            );
+        }
         client.add_extn_sender(extn_id, symbol, extn_tx);
         Ok(())
     }
 
+    /// Stops a started channel by closing its receiver and waits for the `start` method of the
+    /// channel to return. Channels are expected to return from `start` once the receiver is closed.
+    /// Returns [RippleError::TimedOut] if the channel didnt stop in time and
+    /// [RippleError::NotAvailable] if there is no started channel for the given id.
+    pub async fn stop_channel(&self, extn_id: ExtnId) -> Result<(), RippleError> {
+        let started = {
+            self.started_channels
+                .write()
+                .unwrap()
+                .remove(&extn_id.to_string())
+        };
+        let started = match started {
+            Some(started) => started,
+            None => return Err(RippleError::NotAvailable),
+        };
+        started.shutdown.close();
+        let deadline = Instant::now() + Duration::from_millis(EXTN_STOP_TIMEOUT_MS);
+        while !started.handle.is_finished() {
+            if Instant::now() >= deadline {
+                error!("{} channel did not stop in time", extn_id.to_string());
+                return Err(RippleError::TimedOut);
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        if started.handle.join().is_err() {
+            error!("{} channel panicked while stopping", extn_id.to_string());
+        }
+        Ok(())
+    }
+
+    /// Stops every started channel, failures are logged and do not prevent the remaining
+    /// channels from being stopped.
+    pub async fn stop_all_channels(&self) {
+        let ids: Vec<String> = self
+            .started_channels
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        for id in ids {
+            match ExtnId::try_from(id.clone()) {
+                Ok(extn_id) => {
+                    if let Err(e) = self.stop_channel(extn_id).await {
+                        error!("failed to stop {} channel {:?}", id, e);
+                    }
+                }
+                Err(e) => error!("invalid started channel id {} {:?}", id, e),
+            }
+        }
+    }
+
+    /// Starts a deferred channel after bootstrap, the channel is removed from the deferred list.
+    /// Returns [RippleError::NotAvailable] if there is no deferred channel for the given id.
+    pub fn start_deferred(
+        &mut self,
+        extn_id: ExtnId,
+        client: RippleClient,
+    ) -> Result<(), RippleError> {
+        let channel = {
+            let mut deferred_channels = self.deferred_channels.write().unwrap();
+            deferred_channels
+                .iter()
+                .position(|x| x.extn_id.eq(&extn_id))
+                .map(|index| deferred_channels.remove(index))
+        };
+        match channel {
+            Some(channel) => self.start_channel(channel, client),
+            None => {
+                error!("No deferred channel found for {}", extn_id.to_string());
+                Err(RippleError::NotAvailable)
+            }
+        }
+    }
+
     pub fn extend_methods(&self, methods: Methods) {
         let mut methods_state = self.extn_methods.write().unwrap();
         let _ = methods_state.merge(methods);
@@ -218,3 +423,166 @@ impl ExtnState {
         self.extn_methods.read().unwrap().clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::{async_channel::Receiver as CReceiver, extn::extn_id::ExtnClassId};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn get_symbol(id: &str, required: bool) -> ExtnSymbol {
+        ExtnSymbol {
+            id: id.to_owned(),
+            uses: Vec::new(),
+            fulfills: Vec::new(),
+            config: None,
+            required,
+        }
+    }
+
+    #[test]
+    fn test_validate_symbol_ids() {
+        let library_ids = vec!["ripple:channel:device:thunder".to_owned()];
+        let symbols = vec![
+            get_symbol("ripple:channel:device:thunder", true),
+            get_symbol("ripple:channel:device:nonexistent", false),
+        ];
+        assert!(validate_symbol_ids("libthunder", &symbols, &library_ids).is_ok());
+
+        let symbols = vec![get_symbol("ripple:channel:device:nonexistent", true)];
+        assert!(matches!(
+            validate_symbol_ids("libthunder", &symbols, &library_ids),
+            Err(RippleError::BootstrapError)
+        ));
+    }
+
+    fn get_extn_state() -> ExtnState {
+        ExtnState::new(ChannelsState::new(), ExtnManifest::default())
+    }
+
+    fn stoppable_start(_: ExtnSender, receiver: CReceiver<CExtnMessage>) {
+        while !receiver.is_closed() {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_channel() {
+        let mut state = get_extn_state();
+        let id = ExtnId::new_channel(ExtnClassId::Distributor, "stoppable".into());
+        let channel = PreLoadedExtnChannel {
+            channel: Box::new(ExtnChannel {
+                start: stoppable_start,
+            }),
+            extn_id: id.clone(),
+            symbol: get_symbol(&id.to_string(), false),
+        };
+        let client = RippleClient::new(ChannelsState::new());
+        assert!(state.start_channel(channel, client).is_ok());
+
+        assert!(state.stop_channel(id.clone()).await.is_ok());
+        assert!(matches!(
+            state.stop_channel(id).await,
+            Err(RippleError::NotAvailable)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stop_all_channels() {
+        let mut state = get_extn_state();
+        for name in ["first", "second"] {
+            let id = ExtnId::new_channel(ExtnClassId::Distributor, name.into());
+            let channel = PreLoadedExtnChannel {
+                channel: Box::new(ExtnChannel {
+                    start: stoppable_start,
+                }),
+                extn_id: id.clone(),
+                symbol: get_symbol(&id.to_string(), false),
+            };
+            let client = RippleClient::new(ChannelsState::new());
+            assert!(state.start_channel(channel, client).is_ok());
+        }
+
+        state.stop_all_channels().await;
+        assert!(state.started_channels.read().unwrap().is_empty());
+    }
+
+    static STUB_CHANNEL_STARTED: AtomicBool = AtomicBool::new(false);
+
+    fn stub_start(_: ExtnSender, _: CReceiver<CExtnMessage>) {
+        STUB_CHANNEL_STARTED.store(true, Ordering::SeqCst);
+    }
+
+    #[tokio::test]
+    async fn test_start_deferred() {
+        let mut state = get_extn_state();
+        let id = ExtnId::new_channel(ExtnClassId::Distributor, "stub".into());
+        state
+            .deferred_channels
+            .write()
+            .unwrap()
+            .push(PreLoadedExtnChannel {
+                channel: Box::new(ExtnChannel { start: stub_start }),
+                extn_id: id.clone(),
+                symbol: get_symbol(&id.to_string(), false),
+            });
+        let client = RippleClient::new(ChannelsState::new());
+
+        assert!(state.start_deferred(id.clone(), client.clone()).is_ok());
+        assert!(state.deferred_channels.read().unwrap().is_empty());
+        let mut retries = 0;
+        while !STUB_CHANNEL_STARTED.load(Ordering::SeqCst) && retries < 50 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            retries += 1;
+        }
+        assert!(STUB_CHANNEL_STARTED.load(Ordering::SeqCst));
+
+        // already started channels are no longer deferred
+        assert!(matches!(
+            state.start_deferred(id, client),
+            Err(RippleError::NotAvailable)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_ready_timeout() {
+        let state = get_extn_state();
+        let id = ExtnId::new_channel(ExtnClassId::Device, "test".into());
+        let result = state
+            .wait_for_ready(id.clone(), Duration::from_millis(50))
+            .await;
+        assert!(matches!(result, Err(RippleError::TimedOut)));
+        assert!(state.get_extn_status_listener(id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_ready_already_ready() {
+        let state = get_extn_state();
+        let id = ExtnId::new_channel(ExtnClassId::Device, "test".into());
+        state.update_extn_status(id.clone(), ExtnStatus::Ready);
+        assert!(state
+            .wait_for_ready(id, Duration::from_millis(50))
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_snapshot_statuses() {
+        let state = get_extn_state();
+        let device = ExtnId::new_channel(ExtnClassId::Device, "test".into());
+        let gateway = ExtnId::new_channel(ExtnClassId::Gateway, "test".into());
+        state.update_extn_status(device.clone(), ExtnStatus::Ready);
+        state.update_extn_status(gateway.clone(), ExtnStatus::Interrupted);
+
+        let snapshot = state.snapshot_statuses();
+        assert_eq!(snapshot.len(), 2);
+        assert!(matches!(
+            snapshot.get(&device.to_string()),
+            Some(ExtnStatus::Ready)
+        ));
+        assert!(matches!(
+            snapshot.get(&gateway.to_string()),
+            Some(ExtnStatus::Interrupted)
+        ));
+    }
+}
@@ -32,6 +32,7 @@ use ripple_sdk::{
     },
     utils::error::RippleError,
 };
+use serde::Serialize;
 use serde_json::Value;
 use std::{
     collections::HashMap,
@@ -40,6 +41,27 @@ use std::{
 
 use openrpc_validator::FireboltOpenRpc as FireboltOpenRpcValidator;
 
+/// A value in the request params that does not conform to the method's OpenRPC schema.
+/// `path` is the JSON pointer to the value, empty when the params object itself failed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
+impl SchemaError {
+    pub fn collect(schema: &JSONSchema, value: &Value) -> Result<(), Vec<SchemaError>> {
+        schema.validate(value).map_err(|errors| {
+            errors
+                .map(|error| SchemaError {
+                    path: error.instance_path.to_string(),
+                    message: error.to_string(),
+                })
+                .collect()
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ApiSurface {
     Firebolt,
@@ -437,18 +459,14 @@ impl OpenRpcState {
         let _ = json_cache.insert(method, schema);
     }
 
-    pub fn validate_schema(&self, method: &str, value: &Value) -> Result<(), Option<String>> {
+    pub fn validate_schema(
+        &self,
+        method: &str,
+        value: &Value,
+    ) -> Result<(), Option<Vec<SchemaError>>> {
         let json_cache = self.json_schema_cache.read().unwrap();
         if let Some(schema) = json_cache.get(method) {
-            if let Err(e) = schema.validate(value) {
-                let mut error_string = String::new();
-                for error in e {
-                    error_string.push_str(&format!("{} ", error));
-                }
-                Err(Some(error_string))
-            } else {
-                Ok(())
-            }
+            SchemaError::collect(schema, value).map_err(Some)
         } else {
             Err(None)
         }
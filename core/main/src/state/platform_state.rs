@@ -26,6 +26,7 @@ use ripple_sdk::{
         },
         protocol::BridgeProtocolRequest,
         session::SessionAdjective,
+        status_update::ExtnStatus,
     },
     extn::{extn_client_message::ExtnMessage, extn_id::ExtnId},
     framework::{ripple_contract::RippleContract, RippleResponse},
@@ -48,8 +49,8 @@ use crate::{
 };
 
 use super::{
-    cap::cap_state::CapState, metrics_state::MetricsState, openrpc_state::OpenRpcState,
-    ripple_cache::RippleCache, session_state::SessionState,
+    cap::cap_state::CapState, extn_state::ExtnStatusMap, metrics_state::MetricsState,
+    openrpc_state::OpenRpcState, ripple_cache::RippleCache, session_state::SessionState,
 };
 
 /// Platform state encapsulates the internal state of the Ripple Main application.
@@ -107,6 +108,7 @@ pub struct PlatformState {
     pub ripple_cache: RippleCache,
     pub version: Option<String>,
     pub endpoint_state: EndpointBrokerState,
+    extn_status_map: ExtnStatusMap,
 }
 
 impl PlatformState {
@@ -129,8 +131,10 @@ impl PlatformState {
             device_manifest: manifest.clone(),
             ripple_client: client.clone(),
             app_library_state: AppLibraryState::new(app_library),
-            app_events_state: AppEventsState::default(),
-            provider_broker_state: ProviderBrokerState::default(),
+            app_events_state: AppEventsState::default()
+                .with_event_queue(manifest.get_event_queue_configuration()),
+            provider_broker_state: ProviderBrokerState::default()
+                .with_invoke_timeout_ms(manifest.get_provider_invoke_timeout_ms()),
             app_manager_state: AppManagerState::new(&manifest.configuration.saved_dir),
             open_rpc_state: OpenRpcState::new(Some(exclusory), extn_sdks, provider_registations),
             router_state: RouterState::new(),
@@ -140,6 +144,7 @@ impl PlatformState {
             ripple_cache: RippleCache::default(),
             version,
             endpoint_state: EndpointBrokerState::new(broker_sender, rule_engine, client),
+            extn_status_map: ExtnStatusMap::default(),
         }
     }
 
@@ -167,6 +172,15 @@ impl PlatformState {
         self.device_manifest.clone()
     }
 
+    pub fn get_extn_status_map(&self) -> ExtnStatusMap {
+        self.extn_status_map.clone()
+    }
+
+    /// Returns a copy of the last reported status of every extension.
+    pub fn get_extn_statuses(&self) -> HashMap<String, ExtnStatus> {
+        self.extn_status_map.read().unwrap().clone()
+    }
+
     pub fn get_client(&self) -> RippleClient {
         self.ripple_client.clone()
     }
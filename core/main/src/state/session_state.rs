@@ -117,6 +117,10 @@ impl SessionState {
         let _ = session_state.insert(account_session);
     }
 
+    pub fn clear_account_session(&self) {
+        let _ = self.account_session.write().unwrap().take();
+    }
+
     pub fn get_account_session(&self) -> Option<AccountSession> {
         let session_state = self.account_session.read().unwrap();
         if let Some(session) = session_state.clone() {
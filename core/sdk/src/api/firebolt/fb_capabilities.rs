@@ -15,7 +15,10 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::hash::{Hash, Hasher};
+use std::{
+    hash::{Hash, Hasher},
+    sync::OnceLock,
+};
 
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -126,6 +129,41 @@ impl FireboltCap {
     }
 }
 
+/// Structured form of a `xrn:firebolt:capability:<module>:<name>` capability string.
+/// Parsing trims and lowercases the input so differently cased constants resolve to the
+/// same capability, anything not matching the shape is rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FireboltCapability {
+    pub module: String,
+    pub name: String,
+}
+
+static FIREBOLT_CAPABILITY_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+impl FireboltCapability {
+    pub fn parse(cap: &str) -> Option<FireboltCapability> {
+        let normalized = cap.trim().to_lowercase();
+        let pattern = FIREBOLT_CAPABILITY_PATTERN.get_or_init(|| {
+            Regex::new(r"^xrn:firebolt:capability:([a-z0-9-]+):([a-z0-9-]+)$").unwrap()
+        });
+        let captures = pattern.captures(&normalized)?;
+        Some(FireboltCapability {
+            module: captures[1].to_owned(),
+            name: captures[2].to_owned(),
+        })
+    }
+
+    pub fn normalize(cap: &str) -> Option<String> {
+        FireboltCapability::parse(cap).map(|c| c.to_string())
+    }
+}
+
+impl std::fmt::Display for FireboltCapability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "xrn:firebolt:capability:{}:{}", self.module, self.name)
+    }
+}
+
 #[derive(Eq, Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum CapabilityRole {
@@ -443,6 +481,8 @@ pub const JSON_RPC_STANDARD_ERROR_INVALID_PARAMS: i32 = -32602;
 
 pub const JSON_RPC_STANDARD_ERROR_METHOD_NOT_FOUND: i32 = -32601;
 
+pub const JSON_RPC_SERVER_ERROR_TIMED_OUT: i32 = -32000;
+
 pub const CAPABILITY_GRANT_DENIED: i32 = -40400;
 
 pub const CAPABILITY_UNGRANTED: i32 = -40401;
@@ -615,6 +655,35 @@ mod tests {
         assert_eq!(cap, Some(FireboltCap::Short("account:session".to_string())));
     }
 
+    #[test]
+    fn test_firebolt_capability_parse_valid() {
+        let cap = FireboltCapability::parse("xrn:firebolt:capability:localization:postal-code");
+        assert_eq!(
+            cap,
+            Some(FireboltCapability {
+                module: "localization".to_owned(),
+                name: "postal-code".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_firebolt_capability_parse_malformed() {
+        assert!(FireboltCapability::parse("xrn:firebolt:capability:account").is_none());
+        assert!(FireboltCapability::parse("xrn:firebolt:capabilty:account:session").is_none());
+        assert!(FireboltCapability::parse("account:session").is_none());
+        assert!(FireboltCapability::parse("xrn:firebolt:capability:account:session:x").is_none());
+        assert!(FireboltCapability::parse("xrn:firebolt:capability:account:ses sion").is_none());
+    }
+
+    #[test]
+    fn test_firebolt_capability_normalize() {
+        assert_eq!(
+            FireboltCapability::normalize("  XRN:Firebolt:Capability:Input:Keyboard "),
+            Some("xrn:firebolt:capability:input:keyboard".to_owned())
+        );
+    }
+
     #[test]
     fn test_firebolt_cap_from_vec_string() {
         let cap_list = vec!["account:session".to_string()];
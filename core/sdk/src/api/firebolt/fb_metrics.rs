@@ -1177,6 +1177,7 @@ mod tests {
             method: "some method".to_string(),
             cid: Some("test_cid".to_string()),
             gateway_secure: true,
+            trace_id: None,
         };
 
         let metrics_request = MetricsRequest {
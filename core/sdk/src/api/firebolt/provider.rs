@@ -143,6 +143,10 @@ impl ProviderResponsePayload {
 pub struct ProviderRequest {
     pub correlation_id: String,
     pub parameters: ProviderRequestPayload,
+    /// Whether the caller connected through the secure gateway, so providers can decide
+    /// how far to trust the request
+    #[serde(default)]
+    pub gateway_secure: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -26,12 +26,16 @@ use crate::{
     api::firebolt::{fb_general::ListenRequest, fb_openrpc::FireboltOpenRpcMethod},
     extn::extn_client_message::{ExtnPayload, ExtnPayloadProvider, ExtnRequest},
     framework::ripple_contract::RippleContract,
+    utils::error::RippleError,
 };
 
 #[derive(Debug, Clone, Default)]
 pub struct CallerSession {
     pub session_id: Option<String>,
     pub app_id: Option<String>,
+    pub trace_id: Option<String>,
+    /// Whether the caller connected through the secure gateway
+    pub gateway_secure: bool,
 }
 
 impl From<CallContext> for CallerSession {
@@ -39,6 +43,8 @@ impl From<CallContext> for CallerSession {
         CallerSession {
             session_id: Some(ctx.session_id),
             app_id: Some(ctx.app_id),
+            trace_id: ctx.trace_id,
+            gateway_secure: ctx.gateway_secure,
         }
     }
 }
@@ -64,6 +70,9 @@ pub struct CallContext {
     pub method: String,
     pub cid: Option<String>,
     pub gateway_secure: bool,
+    /// Correlates a single Firebolt call across the gateway, broker and extension logs
+    #[serde(default)]
+    pub trace_id: Option<String>,
 }
 
 impl CallContext {
@@ -88,14 +97,29 @@ impl CallContext {
             method,
             cid,
             gateway_secure,
+            trace_id: None,
         }
     }
 
+    pub fn with_trace_id(mut self, trace_id: String) -> CallContext {
+        self.trace_id = Some(trace_id);
+        self
+    }
+
+    /// Id of the connection which made the call, the `cid` when present otherwise the
+    /// `session_id`. Use it to correlate responses and events with the caller's connection.
     pub fn get_id(&self) -> String {
-        if let Some(cid) = &self.cid {
-            return cid.clone();
+        self.get_grouping_id(false)
+    }
+
+    /// Like [CallContext::get_id] but `prefer_session` returns the `session_id` even when a
+    /// `cid` is present. Use it to group calls per app session, e.g. for metrics, where the
+    /// connections of one session should count together.
+    pub fn get_grouping_id(&self, prefer_session: bool) -> String {
+        match &self.cid {
+            Some(cid) if !prefer_session => cid.clone(),
+            _ => self.session_id.clone(),
         }
-        self.session_id.clone()
     }
 }
 
@@ -110,6 +134,7 @@ impl crate::Mockable for CallContext {
             method: "module.method".to_owned(),
             cid: Some("cid".to_owned()),
             gateway_secure: true,
+            trace_id: None,
         }
     }
 }
@@ -167,6 +192,16 @@ impl ApiMessage {
     }
 }
 
+impl crate::Mockable for ApiMessage {
+    fn mock() -> Self {
+        ApiMessage::new(
+            ApiProtocol::JsonRpc,
+            json!({"jsonrpc": "2.0", "id": 1, "result": {}}).to_string(),
+            "1".to_owned(),
+        )
+    }
+}
+
 #[derive(Deserialize)]
 struct ApiBaseRequest {
     jsonrpc: Option<String>,
@@ -224,6 +259,42 @@ impl Default for JsonRpcApiResponse {
     }
 }
 
+impl JsonRpcApiResponse {
+    /// Builds a response which carries exactly one of `result` or `error`, anything else is not
+    /// a valid JSON-RPC response.
+    pub fn new(
+        id: Option<u64>,
+        result: Option<Value>,
+        error: Option<Value>,
+    ) -> Result<Self, RippleError> {
+        if result.is_some() == error.is_some() {
+            return Err(RippleError::InvalidOutput);
+        }
+        Ok(JsonRpcApiResponse {
+            id,
+            result,
+            error,
+            ..Default::default()
+        })
+    }
+
+    pub fn success(id: Option<u64>, result: Value) -> Self {
+        JsonRpcApiResponse {
+            id,
+            result: Some(result),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(id: Option<u64>, error: Value) -> Self {
+        JsonRpcApiResponse {
+            id,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
 impl crate::Mockable for JsonRpcApiResponse {
     fn mock() -> Self {
         JsonRpcApiResponse {
@@ -329,10 +400,31 @@ impl RpcRequest {
             stats: RpcStats::default(),
         }
     }
+
+    /// Module portion of the method, the part before the first `.`.
+    /// A method without a `.` is returned as is.
+    pub fn module(&self) -> &str {
+        self.method
+            .split_once('.')
+            .map_or(self.method.as_str(), |(module, _)| module)
+    }
+
+    /// Method name without its module, the part after the last `.`.
+    /// A method without a `.` is returned as is.
+    pub fn method_name(&self) -> &str {
+        self.method
+            .rsplit_once('.')
+            .map_or(self.method.as_str(), |(_, name)| name)
+    }
+
     /// Serializes a parameter so that the given ctx becomes the first list in a json array of
     /// parameters. Each rpc handler will get the call context as the first param and
     /// the actual request parameter as the second param.
     ///
+    /// The request parameter is always nested as a single element, so an array parameter becomes
+    /// `[ctx, [a, b]]`. Use [RpcRequest::prepend_ctx_spread] when the array holds positional
+    /// arguments.
+    ///
     /// # Arguments
     ///
     /// * `req_params` - The request parameter that becomes the second handler parameter
@@ -346,6 +438,26 @@ impl RpcRequest {
         json!(ps).to_string()
     }
 
+    /// Same as [RpcRequest::prepend_ctx] except that an array parameter is treated as a list of
+    /// positional arguments and spread after the ctx, `[a, b]` becomes `[ctx, a, b]`.
+    /// Any other parameter is nested exactly like [RpcRequest::prepend_ctx] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `req_params` - The request parameter, or the positional handler parameters
+    /// * `ctx` - Context around the call which becomes the first handler parameter
+    pub fn prepend_ctx_spread(req_params: Option<Value>, ctx: &CallContext) -> String {
+        match req_params {
+            Some(Value::Array(args)) => {
+                let mut ps = Vec::<Value>::with_capacity(args.len() + 1);
+                ps.push(json!(ctx));
+                ps.extend(args);
+                json!(ps).to_string()
+            }
+            other => Self::prepend_ctx(other, ctx),
+        }
+    }
+
     /// Parses a json string into an RpcRequest
     /// Checks if jsonrpc field is present in order to determine
     /// which protocol this message is using.
@@ -370,6 +482,12 @@ impl RpcRequest {
         if !base.is_jsonrpc() {
             return Err(RequestParseError {});
         }
+        // Callers may pass their own trace id to correlate the call with their logs
+        let trace_id = parsed
+            .get("traceId")
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
         let jsonrpc_req = serde_json::from_value::<JsonRpcApiRequest>(parsed)
             .map_err(|_| RequestParseError {})?;
 
@@ -384,6 +502,11 @@ impl RpcRequest {
             method.clone(),
             cid,
             gateway_secure,
+        )
+        .with_trace_id(trace_id);
+        debug!(
+            "parsed request method={} trace_id={:?}",
+            method, ctx.trace_id
         );
         let ps = RpcRequest::prepend_ctx(jsonrpc_req.params, &ctx);
         Ok(RpcRequest::new(method, ps, ctx))
@@ -452,6 +575,21 @@ pub enum RpcGatewayCommand {
     },
 }
 
+impl crate::Mockable for RpcGatewayCommand {
+    fn mock() -> Self {
+        RpcGatewayCommand::Handle {
+            req: json!({"jsonrpc": "2.0", "id": 1, "method": "module.method", "params": {}})
+                .to_string(),
+            req_id: "1".to_owned(),
+            ctx: ClientContext {
+                session_id: "session_id".to_owned(),
+                app_id: "some_app_id".to_owned(),
+                gateway_secure: true,
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ClientContext {
     pub session_id: String,
@@ -486,12 +624,15 @@ mod tests {
             method: "method123".to_string(),
             cid: Some("cid123".to_string()),
             gateway_secure: true,
+            trace_id: None,
         };
 
         let caller_session: CallerSession = ctx.into();
 
         assert_eq!(caller_session.session_id, Some("session123".to_string()));
         assert_eq!(caller_session.app_id, Some("app123".to_string()));
+        assert_eq!(caller_session.trace_id, None);
+        assert!(caller_session.gateway_secure);
     }
 
     #[test]
@@ -505,6 +646,7 @@ mod tests {
             method: "method123".to_string(),
             cid: Some("cid123".to_string()),
             gateway_secure: true,
+            trace_id: None,
         };
 
         let app_identification: AppIdentification = ctx.into();
@@ -581,6 +723,18 @@ mod tests {
         assert_eq!(id, "session_id".to_string());
     }
 
+    #[test]
+    fn test_get_grouping_id() {
+        let mut ctx = CallContext::mock();
+        ctx.cid = Some("cid".to_owned());
+        assert_eq!(ctx.get_grouping_id(false), "cid");
+        assert_eq!(ctx.get_grouping_id(true), "session_id");
+
+        ctx.cid = None;
+        assert_eq!(ctx.get_grouping_id(false), "session_id");
+        assert_eq!(ctx.get_grouping_id(true), "session_id");
+    }
+
     #[test]
     fn test_is_errors() {
         let api_message = ApiMessage::new(
@@ -696,6 +850,65 @@ mod tests {
     //     assert_eq!(rpc_request.ctx.cid, None);
     //     assert!(rpc_request.ctx.gateway_secure);
     // }
+    #[test]
+    fn test_json_rpc_api_response_new() {
+        assert!(JsonRpcApiResponse::new(Some(1), None, None).is_err());
+        assert!(JsonRpcApiResponse::new(Some(1), Some(json!(1)), Some(json!({}))).is_err());
+
+        let response = JsonRpcApiResponse::new(Some(1), Some(Value::Null), None).unwrap();
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            json!({"jsonrpc": "2.0", "id": 1, "result": null})
+        );
+        assert_eq!(
+            serde_json::to_value(JsonRpcApiResponse::error(Some(2), json!({"code": -1}))).unwrap(),
+            json!({"jsonrpc": "2.0", "id": 2, "error": {"code": -1}})
+        );
+        assert_eq!(
+            serde_json::to_value(JsonRpcApiResponse::success(None, json!("ok"))).unwrap(),
+            json!({"jsonrpc": "2.0", "id": null, "result": "ok"})
+        );
+    }
+
+    #[test]
+    fn test_rpc_request_module_and_method_name() {
+        for (method, module, name) in [
+            ("player.load", "player", "load"),
+            ("device.info", "device", "info"),
+            ("ping", "ping", "ping"),
+        ] {
+            let mut request = RpcRequest::mock();
+            request.method = method.to_owned();
+            assert_eq!(request.module(), module);
+            assert_eq!(request.method_name(), name);
+        }
+    }
+
+    #[test]
+    fn test_rpc_request_parse_trace_id() {
+        let parse = |json: Value| {
+            RpcRequest::parse(
+                json.to_string(),
+                "app_id".to_owned(),
+                "session_id".to_owned(),
+                "request_id".to_owned(),
+                None,
+                true,
+            )
+            .unwrap()
+        };
+        let request = parse(json!({"jsonrpc": "2.0", "id": 1, "method": "device.info"}));
+        assert!(request.ctx.trace_id.is_some());
+
+        let request = parse(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "device.info",
+            "traceId": "abc"
+        }));
+        assert_eq!(request.ctx.trace_id, Some("abc".to_owned()));
+    }
+
     #[test]
     fn test_extn_request_rpc() {
         let call_context = CallContext {
@@ -707,6 +920,7 @@ mod tests {
             method: "some_method".to_string(),
             cid: Some("some_cid".to_string()),
             gateway_secure: true,
+            trace_id: None,
         };
 
         let rpc_request = RpcRequest {
@@ -777,4 +991,59 @@ mod tests {
         let request = serde_json::from_str::<ListenRequest>(&new.params_json).unwrap();
         assert!(!request.listen);
     }
+
+    #[test]
+    fn test_gateway_mocks_serialize() {
+        let api_message = ApiMessage::mock();
+        assert!(!api_message.is_error());
+        let value = serde_json::to_value(&api_message).unwrap();
+        assert_eq!(
+            serde_json::from_value::<ApiMessage>(value).unwrap(),
+            api_message
+        );
+
+        let route = RpcGatewayCommand::Route {
+            req: RpcRequest::mock(),
+        };
+        for command in [RpcGatewayCommand::mock(), route] {
+            let value = serde_json::to_value(&command).unwrap();
+            assert!(serde_json::from_value::<RpcGatewayCommand>(value).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_prepend_ctx_nests_params() {
+        let ctx = CallContext::mock();
+        let params = json!({"listen": true});
+        let ps: Vec<Value> =
+            serde_json::from_str(&RpcRequest::prepend_ctx(Some(params.clone()), &ctx)).unwrap();
+        assert_eq!(ps, vec![json!(ctx), params.clone()]);
+
+        let ps: Vec<Value> =
+            serde_json::from_str(&RpcRequest::prepend_ctx_spread(Some(params.clone()), &ctx))
+                .unwrap();
+        assert_eq!(ps, vec![json!(ctx), params]);
+
+        let ps: Vec<Value> =
+            serde_json::from_str(&RpcRequest::prepend_ctx(Some(json!([1, "two"])), &ctx)).unwrap();
+        assert_eq!(ps, vec![json!(ctx), json!([1, "two"])]);
+    }
+
+    #[test]
+    fn test_prepend_ctx_spread_array_params() {
+        let ctx = CallContext::mock();
+        let ps: Vec<Value> = serde_json::from_str(&RpcRequest::prepend_ctx_spread(
+            Some(json!([1, "two", {"three": 3}])),
+            &ctx,
+        ))
+        .unwrap();
+        assert_eq!(
+            ps,
+            vec![json!(ctx), json!(1), json!("two"), json!({"three": 3})]
+        );
+
+        let ps: Vec<Value> =
+            serde_json::from_str(&RpcRequest::prepend_ctx_spread(None, &ctx)).unwrap();
+        assert_eq!(ps, vec![json!(ctx)]);
+    }
 }
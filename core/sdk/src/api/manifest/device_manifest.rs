@@ -26,7 +26,10 @@ use std::{
 
 use crate::{
     api::{
-        device::device_user_grants_data::{GrantExclusionFilter, GrantPolicies},
+        device::{
+            device_request::PowerState,
+            device_user_grants_data::{GrantExclusionFilter, GrantLifespan, GrantPolicies},
+        },
         distributor::distributor_privacy::DataEventType,
         firebolt::fb_capabilities::FireboltPermission,
         storage_property::StorageProperty,
@@ -37,6 +40,9 @@ use crate::{
 use super::{apps::AppManifest, exclusory::ExclusoryImpl, remote_feature::FeatureFlag};
 pub const PARTNER_EXCLUSION_REFRESH_TIMEOUT: u32 = 12 * 60 * 60; // 12 hours
 pub const METRICS_LOGGING_PERCENTAGE_DEFAULT: u32 = 10;
+pub const EVENT_QUEUE_MAX_PENDING_DEFAULT: usize = 64;
+pub const PROVIDER_INVOKE_TIMEOUT_MS_DEFAULT: u64 = 15000;
+pub const REQUIRED_ENDPOINTS_TIMEOUT_MS_DEFAULT: u64 = 30000;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct RippleConfiguration {
@@ -68,6 +74,15 @@ pub struct RippleConfiguration {
     pub partner_exclusion_refresh_timeout: u32,
     #[serde(default = "metrics_logging_percentage_default")]
     pub metrics_logging_percentage: u32,
+    #[serde(default)]
+    pub event_queue: EventQueueConfiguration,
+    /// How long a provider session waits for the provider app before the caller gets a timeout.
+    /// Sessions waiting on user input, like keyboard and challenges, never time out.
+    #[serde(default = "provider_invoke_timeout_ms_default")]
+    pub provider_invoke_timeout_ms: u64,
+    /// How long bootstrap waits for the broker endpoints marked as required to connect
+    #[serde(default = "required_endpoints_timeout_ms_default")]
+    pub required_endpoints_timeout_ms: u64,
 }
 
 fn partner_exclusion_refresh_timeout_default() -> u32 {
@@ -78,6 +93,14 @@ fn metrics_logging_percentage_default() -> u32 {
     METRICS_LOGGING_PERCENTAGE_DEFAULT
 }
 
+fn provider_invoke_timeout_ms_default() -> u64 {
+    PROVIDER_INVOKE_TIMEOUT_MS_DEFAULT
+}
+
+fn required_endpoints_timeout_ms_default() -> u64 {
+    REQUIRED_ENDPOINTS_TIMEOUT_MS_DEFAULT
+}
+
 #[derive(Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct CapabilityConfiguration {
@@ -87,6 +110,21 @@ pub struct CapabilityConfiguration {
     pub grant_exclusion_filters: Vec<GrantExclusionFilter>,
     #[serde(default)]
     pub dependencies: HashMap<FireboltPermission, Vec<FireboltPermission>>,
+    /// User grant lifespans which are cleared when the device enters a power state
+    #[serde(default)]
+    pub power_state_grant_cleanup: Option<HashMap<PowerState, Vec<GrantLifespan>>>,
+}
+
+impl CapabilityConfiguration {
+    /// Returns the grant lifespans to be cleared when the device transitions to the given power
+    /// state. Without configuration the power active grants are cleared when the device is On.
+    pub fn get_grant_lifespans_to_clear(&self, power_state: &PowerState) -> Vec<GrantLifespan> {
+        match &self.power_state_grant_cleanup {
+            Some(cleanup) => cleanup.get(power_state).cloned().unwrap_or_default(),
+            None if matches!(power_state, PowerState::On) => vec![GrantLifespan::PowerActive],
+            None => Vec::new(),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
@@ -536,6 +574,10 @@ pub struct RippleFeatures {
     pub cloud_permissions: bool,
     #[serde(default)]
     pub catalog_uninstalls_enabled: FeatureFlag,
+    /// Rejects params that fail the OpenRPC schema with `Invalid params` and reports the path
+    /// of each failing value in the error data.
+    #[serde(default)]
+    pub strict_params_validation: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -545,6 +587,38 @@ pub enum IntentValidation {
     FailOpen,
 }
 
+/// Limits the events waiting for an app session that is not reading them fast enough.
+/// Once `max_pending` events are waiting, `overflow_policy` decides which event is dropped.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventQueueConfiguration {
+    #[serde(default = "event_queue_max_pending_default")]
+    pub max_pending: usize,
+    #[serde(default)]
+    pub overflow_policy: EventOverflowPolicy,
+}
+
+impl Default for EventQueueConfiguration {
+    fn default() -> Self {
+        EventQueueConfiguration {
+            max_pending: event_queue_max_pending_default(),
+            overflow_policy: EventOverflowPolicy::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum EventOverflowPolicy {
+    #[default]
+    DropOldest,
+    DropNewest,
+}
+
+fn event_queue_max_pending_default() -> usize {
+    EVENT_QUEUE_MAX_PENDING_DEFAULT
+}
+
 fn default_saved_dir() -> String {
     String::from("/opt/persistent/ripple")
 }
@@ -653,6 +727,7 @@ impl Default for RippleFeatures {
             intent_validation: default_intent_validation(),
             cloud_permissions: default_cloud_permissions(),
             catalog_uninstalls_enabled: Default::default(),
+            strict_params_validation: false,
         }
     }
 }
@@ -737,6 +812,9 @@ impl Default for RippleConfiguration {
             data_governance: Default::default(),
             partner_exclusion_refresh_timeout: partner_exclusion_refresh_timeout_default(),
             metrics_logging_percentage: metrics_logging_percentage_default(),
+            event_queue: Default::default(),
+            provider_invoke_timeout_ms: provider_invoke_timeout_ms_default(),
+            required_endpoints_timeout_ms: required_endpoints_timeout_ms_default(),
         }
     }
 }
@@ -851,6 +929,18 @@ impl DeviceManifest {
     pub fn get_applications_configuration(&self) -> ApplicationsConfiguration {
         self.applications.clone()
     }
+
+    pub fn get_event_queue_configuration(&self) -> EventQueueConfiguration {
+        self.configuration.event_queue.clone()
+    }
+
+    pub fn get_provider_invoke_timeout_ms(&self) -> u64 {
+        self.configuration.provider_invoke_timeout_ms
+    }
+
+    pub fn get_required_endpoints_timeout_ms(&self) -> u64 {
+        self.configuration.required_endpoints_timeout_ms
+    }
 }
 
 #[cfg(test)]
@@ -950,6 +1040,7 @@ pub(crate) mod tests {
                             default: false,
                             remote_key: None,
                         },
+                        strict_params_validation: false,
                     },
                     internal_app_id: Some("test".to_string()),
                     saved_dir: "/opt/persistent/ripple".to_string(),
@@ -958,6 +1049,9 @@ pub(crate) mod tests {
                     },
                     partner_exclusion_refresh_timeout: 43200,
                     metrics_logging_percentage: 10,
+                    event_queue: EventQueueConfiguration::default(),
+                    provider_invoke_timeout_ms: 15000,
+                    required_endpoints_timeout_ms: 30000,
                 },
                 capabilities: CapabilityConfiguration {
                     supported: vec!["main[manage]".to_string(), "test".to_string()],
@@ -968,6 +1062,7 @@ pub(crate) mod tests {
                         catalog: Some("test-catalog".to_string()),
                     }],
                     dependencies: HashMap::new(),
+                    power_state_grant_cleanup: None,
                 },
                 lifecycle: LifecycleConfiguration {
                     app_ready_timeout_ms: 30000,
@@ -999,6 +1094,36 @@ pub(crate) mod tests {
         assert_eq!(internal_app_id, Some("test".to_string()));
     }
 
+    #[test]
+    fn test_get_grant_lifespans_to_clear() {
+        let mut capabilities = DeviceManifest::mock().capabilities;
+        assert_eq!(
+            capabilities.get_grant_lifespans_to_clear(&PowerState::On),
+            vec![GrantLifespan::PowerActive]
+        );
+        assert!(capabilities
+            .get_grant_lifespans_to_clear(&PowerState::Standby)
+            .is_empty());
+
+        capabilities.power_state_grant_cleanup = serde_json::from_str(
+            r#"{"STANDBY": ["powerActive"], "DEEP_SLEEP": ["powerActive", "appActive"]}"#,
+        )
+        .unwrap();
+        // On -> Standby
+        assert_eq!(
+            capabilities.get_grant_lifespans_to_clear(&PowerState::Standby),
+            vec![GrantLifespan::PowerActive]
+        );
+        // On -> DeepSleep
+        assert_eq!(
+            capabilities.get_grant_lifespans_to_clear(&PowerState::DeepSleep),
+            vec![GrantLifespan::PowerActive, GrantLifespan::AppActive]
+        );
+        assert!(capabilities
+            .get_grant_lifespans_to_clear(&PowerState::On)
+            .is_empty());
+    }
+
     #[test]
     fn test_get_form_factor() {
         let manifest = DeviceManifest::mock();
@@ -1110,6 +1235,7 @@ pub(crate) mod tests {
                     default: false,
                     remote_key: None,
                 },
+                strict_params_validation: false,
             }
         );
     }
@@ -99,6 +99,9 @@ pub struct ExtnSymbol {
     pub uses: Vec<String>,
     pub fulfills: Vec<String>,
     pub config: Option<HashMap<String, String>>,
+    /// Bootstrap fails if the extension library does not expose a required symbol
+    #[serde(default)]
+    pub required: bool,
 }
 
 impl ExtnSymbol {
@@ -267,6 +270,7 @@ mod tests {
             uses: vec![],
             fulfills: vec![],
             config: None,
+            required: false,
         };
         let extn_manifest_entry = ExtnManifestEntry {
             path: "relative/path".to_string(),
@@ -319,6 +323,7 @@ mod tests {
             uses: vec![],
             fulfills: vec![],
             config: None,
+            required: false,
         };
 
         let capability = symbol.get_launcher_capability();
@@ -340,6 +345,7 @@ mod tests {
             uses: vec![],
             fulfills: vec![],
             config: None,
+            required: false,
         };
 
         let capability = symbol.get_distributor_capability();
@@ -362,6 +368,7 @@ mod tests {
             uses: vec![],
             fulfills: vec![],
             config: None,
+            required: false,
         };
         let extn_manifest_entry = ExtnManifestEntry {
             path: "relative/path".to_string(),
@@ -386,6 +393,7 @@ mod tests {
             uses: vec![],
             fulfills: vec![],
             config: None,
+            required: false,
         };
         let extn_manifest_entry = ExtnManifestEntry {
             path: "relative/path".to_string(),
@@ -410,6 +418,7 @@ mod tests {
             uses: vec!["config".to_string()],
             fulfills: vec!["test".to_string()],
             config: None,
+            required: false,
         };
         let extn_manifest_entry = ExtnManifestEntry {
             path: "relative/path".to_string(),
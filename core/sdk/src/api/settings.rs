@@ -188,6 +188,7 @@ mod tests {
                 method: "some method".to_string(),
                 cid: Some("test_cid".to_string()),
                 gateway_secure: true,
+                trace_id: None,
             },
             vec![SettingKey::VoiceGuidanceEnabled, SettingKey::ClosedCaptions],
             alias_map,
@@ -214,6 +215,7 @@ mod tests {
                 method: "some method".to_string(),
                 cid: Some("test_cid".to_string()),
                 gateway_secure: true,
+                trace_id: None,
             },
             keys: vec![SettingKey::VoiceGuidanceEnabled, SettingKey::ClosedCaptions],
             alias_map: Some(HashMap::new()),
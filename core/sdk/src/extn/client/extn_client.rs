@@ -1037,6 +1037,7 @@ pub mod tests {
                 uses: Vec::new(),
                 fulfills: Vec::new(),
                 config: None,
+                required: false,
             },
             s,
         );
@@ -1065,6 +1066,7 @@ pub mod tests {
                 uses: Vec::new(),
                 fulfills: Vec::new(),
                 config: None,
+                required: false,
             },
             s,
         );
@@ -1092,6 +1094,7 @@ pub mod tests {
                 uses: Vec::new(),
                 fulfills: vec!["account.session".to_string()],
                 config: None,
+                required: false,
             },
             s,
         );
@@ -1123,6 +1126,7 @@ pub mod tests {
                 uses: Vec::new(),
                 fulfills: vec![RippleContract::Session(SessionAdjective::Device).as_clear_string()],
                 config: None,
+                required: false,
             },
             s,
         );
@@ -1331,6 +1335,7 @@ pub mod tests {
                 uses: Vec::new(),
                 fulfills: vec![RippleContract::DeviceInfo.as_clear_string()],
                 config: Some(HashMap::new()),
+                required: false,
             },
             extn_tx,
         );
@@ -1419,6 +1424,7 @@ pub mod tests {
                 uses: vec![RippleContract::Config.as_clear_string()],
                 fulfills: vec![RippleContract::DeviceInfo.as_clear_string()],
                 config: Some(HashMap::new()),
+                required: false,
             },
             extn_tx,
         );
@@ -1508,6 +1514,7 @@ pub mod tests {
                 ],
                 fulfills: vec![RippleContract::Permissions.as_clear_string()],
                 config: Some(HashMap::new()),
+                required: false,
             },
             dist_tx,
         );
@@ -1535,6 +1542,7 @@ pub mod tests {
                 uses: vec![RippleContract::Config.as_clear_string()],
                 fulfills: vec![RippleContract::DeviceInfo.as_clear_string()],
                 config: Some(HashMap::new()),
+                required: false,
             },
             dev_tx,
         );
@@ -1598,6 +1606,7 @@ pub mod tests {
                 uses: vec!["config".to_string()],
                 fulfills: vec!["permissions".to_string()],
                 config: None,
+                required: false,
             },
             mock_sender.tx,
         );
@@ -2045,6 +2054,7 @@ pub mod tests {
                 uses: vec!["config".to_string()],
                 fulfills: vec!["permissions".to_string()],
                 config: None,
+                required: false,
             },
             mock_sender.tx,
         );
@@ -2184,6 +2194,7 @@ pub mod tests {
                     uses: vec!["config".to_string()],
                     fulfills: vec!["permissions".to_string()],
                     config: None,
+                    required: false,
                 },
                 mock_sender.tx,
             );
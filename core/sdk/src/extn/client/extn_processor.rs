@@ -744,6 +744,7 @@ pub mod tests {
                 uses: vec!["uses".to_string()],
                 fulfills: Vec::new(),
                 config: None,
+                required: false,
             },
             mock_sender.tx,
         );
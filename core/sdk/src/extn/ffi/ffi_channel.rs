@@ -24,6 +24,9 @@ use crate::{extn::client::extn_sender::ExtnSender, utils::error::RippleError};
 use super::ffi_message::CExtnMessage;
 
 /// Generic Extension channel
+///
+/// `start` is run on a dedicated thread and is expected to return once the receiver is closed,
+/// this is how Ripple stops a channel during shutdown.
 #[repr(C)]
 #[derive(Debug)]
 pub struct ExtnChannel {
@@ -40,6 +40,7 @@ pub enum RippleError {
     RuleError,
     ServiceNotReady,
     BrokerError(String),
+    TimedOut,
 }
 impl std::fmt::Display for RippleError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -66,6 +67,7 @@ impl std::fmt::Display for RippleError {
                 let msg = format!("BrokerError {}", msg);
                 write!(f, "{}", msg)
             }
+            RippleError::TimedOut => write!(f, "TimedOut"),
         }
     }
 }
@@ -104,6 +106,7 @@ mod tests {
         custom_error_match("ClientMissing", RippleError::ClientMissing.into());
         custom_error_match("NoResponse", RippleError::NoResponse.into());
         custom_error_match("InvalidAccess", RippleError::InvalidAccess.into());
+        custom_error_match("TimedOut", RippleError::TimedOut.into());
         custom_error_match(
             "Permission AppNotInActiveState",
             RippleError::Permission(DenyReason::AppNotInActiveState).into(),
@@ -15,16 +15,51 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::{str::FromStr, sync::atomic::AtomicU32};
+use std::{
+    str::FromStr,
+    sync::{atomic::AtomicU32, Once},
+};
 
 pub static LOG_COUNTER: AtomicU32 = AtomicU32::new(1);
+static LOGGER_INIT: Once = Once::new();
+/// Set to `json` to emit newline delimited JSON logs instead of the default text format.
+const LOG_FORMAT_ENV: &str = "RIPPLE_LOG_FORMAT";
+
+fn is_json_format() -> bool {
+    std::env::var(LOG_FORMAT_ENV)
+        .map(|format| format.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+fn json_log_line(record: &log::Record, name: &str) -> String {
+    serde_json::json!({
+        "ts": chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "name": name,
+        "msg": record.args().to_string(),
+    })
+    .to_string()
+}
+
+/// The logger can only be installed once per process, later calls are no-ops returning `Ok`
+/// so that tests or extensions which initialize logging again do not fail.
+fn apply_once(dispatch: fern::Dispatch) -> Result<(), fern::InitError> {
+    let mut result = Ok(());
+    LOGGER_INIT.call_once(|| result = dispatch.apply().map_err(fern::InitError::from));
+    result
+}
 
 pub fn init_logger(name: String) -> Result<(), fern::InitError> {
     let log_string: String = std::env::var("RUST_LOG").unwrap_or_else(|_| "debug".into());
     println!("log level {}", log_string);
     let filter = log::LevelFilter::from_str(&log_string).unwrap_or(log::LevelFilter::Info);
-    fern::Dispatch::new()
+    let json = is_json_format();
+    let dispatch = fern::Dispatch::new()
         .format(move |out, message, record| {
+            if json {
+                return out.finish(format_args!("{}", json_log_line(record, &name)));
+            }
             #[cfg(not(feature = "sysd"))]
             return out.finish(format_args!(
                 "{}[{}][{}][{}]-{}",
@@ -52,9 +87,8 @@ pub fn init_logger(name: String) -> Result<(), fern::InitError> {
         .level_for("tower_http", log::LevelFilter::Off)
         .level_for("jsonrpsee_client_transport", log::LevelFilter::Off)
         .level_for("jsonrpsee_core", log::LevelFilter::Off)
-        .chain(std::io::stdout())
-        .apply()?;
-    Ok(())
+        .chain(std::io::stdout());
+    apply_once(dispatch)
 }
 
 pub fn init_and_configure_logger(version: &str, name: String) -> Result<(), fern::InitError> {
@@ -62,8 +96,12 @@ pub fn init_and_configure_logger(version: &str, name: String) -> Result<(), fern
     println!("log level {}", log_string);
     let _version_string = version.to_string();
     let filter = log::LevelFilter::from_str(&log_string).unwrap_or(log::LevelFilter::Info);
-    fern::Dispatch::new()
+    let json = is_json_format();
+    let dispatch = fern::Dispatch::new()
         .format(move |out, message, record| {
+            if json {
+                return out.finish(format_args!("{}", json_log_line(record, &name)));
+            }
             let _v = LOG_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             #[cfg(not(feature = "sysd"))]
             if _v % 100 == 0 {
@@ -117,7 +155,37 @@ pub fn init_and_configure_logger(version: &str, name: String) -> Result<(), fern
         .level_for("tower_http", log::LevelFilter::Off)
         .level_for("jsonrpsee_client_transport", log::LevelFilter::Off)
         .level_for("jsonrpsee_core", log::LevelFilter::Off)
-        .chain(std::io::stdout())
-        .apply()?;
-    Ok(())
+        .chain(std::io::stdout());
+    apply_once(dispatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logger_init_is_idempotent() {
+        assert!(init_and_configure_logger("1.0.0", "gateway".into()).is_ok());
+        assert!(init_and_configure_logger("1.0.0", "gateway".into()).is_ok());
+        assert!(init_logger("extn".into()).is_ok());
+    }
+
+    #[test]
+    fn test_json_log_line() {
+        let line = json_log_line(
+            &log::Record::builder()
+                .args(format_args!("multi \"quoted\"\nmessage"))
+                .level(log::Level::Warn)
+                .target("ripple")
+                .build(),
+            "gateway",
+        );
+        assert_eq!(line.lines().count(), 1);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "ripple");
+        assert_eq!(value["name"], "gateway");
+        assert_eq!(value["msg"], "multi \"quoted\"\nmessage");
+        assert!(value["ts"].is_string());
+    }
 }
@@ -21,12 +21,15 @@ use crate::extn::extn_client_message::{
     ExtnEvent, ExtnMessage, ExtnPayload, ExtnPayloadProvider, ExtnRequest, ExtnResponse,
 };
 use crate::extn::extn_id::ExtnId;
+use crate::extn::ffi::ffi_message::CExtnMessage;
 use crate::framework::ripple_contract::RippleContract;
-use async_channel::unbounded;
+use async_channel::{unbounded, Receiver as CReceiver};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MockEvent {
@@ -104,6 +107,58 @@ pub fn get_mock_extn_client(id: ExtnId) -> ExtnClient {
     ExtnClient::new(receiver, mock_sender)
 }
 
+/// An [ExtnClient] backed by in-memory channels so request processors can be driven from tests.
+/// Requests are built with [MockExtnChannel::request] and passed to the processor directly, the
+/// responses the processor sends back to main are read with [MockExtnChannel::recv_response].
+pub struct MockExtnChannel {
+    id: ExtnId,
+    client: ExtnClient,
+    main_rx: CReceiver<CExtnMessage>,
+}
+
+impl MockExtnChannel {
+    pub fn new(id: ExtnId) -> Self {
+        let (main_tx, main_rx) = unbounded();
+        let (_, client_rx) = unbounded();
+        let sender = ExtnSender::new(main_tx, id.clone(), Vec::new(), Vec::new(), None);
+        MockExtnChannel {
+            id,
+            client: ExtnClient::new(client_rx, sender),
+            main_rx,
+        }
+    }
+
+    pub fn client(&self) -> ExtnClient {
+        self.client.clone()
+    }
+
+    /// Builds a request from main to this client, as the processor would receive it.
+    pub fn request(&self, payload: impl ExtnPayloadProvider) -> ExtnMessage {
+        ExtnMessage {
+            id: Uuid::new_v4().to_string(),
+            requestor: ExtnId::get_main_target("main".into()),
+            target: payload.get_contract(),
+            target_id: Some(self.id.clone()),
+            payload: payload.get_extn_payload(),
+            callback: None,
+            ts: Some(Utc::now().timestamp_millis()),
+        }
+    }
+
+    /// Waits up to a second for the next response sent by the client.
+    pub async fn recv_response(&self) -> Option<ExtnResponse> {
+        let msg = tokio::time::timeout(Duration::from_secs(1), self.main_rx.recv())
+            .await
+            .ok()?
+            .ok()?;
+        let msg: ExtnMessage = msg.try_into().ok()?;
+        match msg.payload {
+            ExtnPayload::Response(response) => Some(response),
+            _ => None,
+        }
+    }
+}
+
 pub fn get_mock_message(payload_type: PayloadType) -> ExtnMessage {
     ExtnMessage {
         id: "test_id".to_string(),
@@ -65,6 +65,7 @@ pub enum BootFailedError {
     BadHostname,
     GetPlatformGatewayFailed,
     ServerStartFailed(MockServerWebSocketError),
+    BadMockServersConfig,
 }
 
 impl Display for BootFailedError {
@@ -81,6 +82,9 @@ impl Display for BootFailedError {
             Self::ServerStartFailed(err) => {
                 format!("Failed to start the WebSocket server. Error: {err}")
             }
+            Self::BadMockServersConfig => {
+                "The mock_servers config must be a JSON object of server names to ports.".to_owned()
+            }
         };
 
         f.write_str(msg.as_str())
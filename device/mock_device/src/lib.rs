@@ -21,6 +21,7 @@ pub mod mock_data;
 pub mod mock_device_controller;
 pub mod mock_device_ffi;
 pub mod mock_device_processor;
+pub mod mock_predicate;
 pub mod mock_server;
 pub mod mock_web_socket_server;
 #[cfg(test)]
@@ -15,17 +15,62 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+use std::collections::HashMap;
+
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct MockConfig {
     pub activate_all_plugins: bool,
+    /// When set, connections must send `Authorization: Bearer <token>` or get a 401
+    #[serde(default)]
+    pub required_bearer_token: Option<String>,
+    /// Records every request which doesn't match a mock so tests can assert on them
+    #[serde(default)]
+    pub strict: bool,
+    /// Number of processed requests kept in the history, zero disables it
+    #[serde(default = "history_size_default")]
+    pub history_size: usize,
+    /// How the query params of a connection are compared with the ones the server requires
+    #[serde(default)]
+    pub query_match_mode: QueryMatchMode,
+    /// State answered to `Controller.1.status@<callsign>` for each callsign, e.g. `deactivated`.
+    /// Takes precedence over `activate_all_plugins`
+    #[serde(default)]
+    pub plugin_states: HashMap<String, String>,
+    /// Answers plain HTTP `GET /healthz` requests on the server port with the server stats
+    #[serde(default)]
+    pub enable_health: bool,
+    /// Seed of the random picks among weighted responses, set it for reproducible runs
+    #[serde(default)]
+    pub rng_seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryMatchMode {
+    /// The connection must send exactly the required query params
+    #[default]
+    Exact,
+    /// The connection must send the required query params and may send others
+    Subset,
+}
+
+fn history_size_default() -> usize {
+    100
 }
 
 impl Default for MockConfig {
     fn default() -> Self {
         Self {
             activate_all_plugins: true,
+            required_bearer_token: None,
+            strict: false,
+            history_size: history_size_default(),
+            query_match_mode: QueryMatchMode::default(),
+            plugin_states: HashMap::new(),
+            enable_health: false,
+            rng_seed: None,
         }
     }
 }
@@ -15,13 +15,21 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use ripple_sdk::log::{debug, error};
+use ripple_sdk::{
+    api::gateway::rpc_gateway_api::JsonRpcApiResponse,
+    log::{debug, error},
+};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
 
 use crate::{
     errors::{LoadMockDataError, MockDeviceError},
+    mock_predicate::ParamPredicate,
     mock_server::{MessagePayload, PayloadType, PayloadTypeError},
     mock_web_socket_server::ThunderRegisterParams,
 };
@@ -34,6 +42,170 @@ pub struct ParamResponse {
     pub result: Option<Value>,
     pub error: Option<Value>,
     pub events: Option<Vec<EventValue>>,
+    /// When this is the only entry for a method it is returned regardless of the request params.
+    /// Defaults to `true` to keep the previous behavior while mock data files are migrated.
+    #[serde(default = "match_any_params_default")]
+    pub match_any_params: bool,
+    /// When set, the entry matches requests whose params satisfy every predicate instead of
+    /// comparing `params` for equality.
+    #[serde(default)]
+    pub predicates: Option<Vec<ParamPredicate>>,
+    /// The entry stops matching once this long has passed since it was added
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+    #[serde(skip)]
+    pub added_at: Option<Instant>,
+    /// The response is sent this long after the request was received
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+    /// Relative chance of this entry being picked among the entries matching the same request.
+    /// Entries without a weight count as 1 once any matching entry has one
+    #[serde(default)]
+    pub weight: Option<u32>,
+}
+
+impl Default for ParamResponse {
+    fn default() -> Self {
+        ParamResponse {
+            params: None,
+            result: None,
+            error: None,
+            events: None,
+            match_any_params: match_any_params_default(),
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
+        }
+    }
+}
+
+fn match_any_params_default() -> bool {
+    true
+}
+
+/// Assembles [MockData] in code rather than from JSON. Entries are added to the method last
+/// passed to [MockDataBuilder::method]. `when_params` starts a new entry, `respond` starts one
+/// when the current entry already has a response, the other setters change the current entry.
+///
+/// ```
+/// use mock_device::mock_data::MockDataBuilder;
+/// use serde_json::json;
+///
+/// let mock_data = MockDataBuilder::new()
+///     .method("org.rdk.System.1.getFriendlyName")
+///     .when_params(json!({"verbose": true}))
+///     .respond(json!("Living Room TV"))
+///     .delay_ms(50)
+///     .when_params(json!({"verbose": false}))
+///     .respond(json!("Living Room"))
+///     .method("org.rdk.System.1.getMode")
+///     .respond_error(json!({"code": -32010, "message": "Unavailable"}))
+///     .build();
+///
+/// assert_eq!(mock_data["org.rdk.System.1.getFriendlyName"].len(), 2);
+/// assert_eq!(mock_data["org.rdk.System.1.getMode"].len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockDataBuilder {
+    method: Option<String>,
+    entries: Vec<(String, ParamResponse)>,
+}
+
+impl MockDataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = Some(method.to_owned());
+        self
+    }
+
+    pub fn when_params(mut self, params: Value) -> Self {
+        self.new_entry().params = Some(params);
+        self
+    }
+
+    pub fn when(mut self, predicates: Vec<ParamPredicate>) -> Self {
+        self.new_entry().predicates = Some(predicates);
+        self
+    }
+
+    pub fn respond(mut self, result: Value) -> Self {
+        self.response_entry().result = Some(result);
+        self
+    }
+
+    pub fn respond_error(mut self, error: Value) -> Self {
+        self.response_entry().error = Some(error);
+        self
+    }
+
+    pub fn event(mut self, data: Value, delay_ms: u64) -> Self {
+        self.current_entry()
+            .events
+            .get_or_insert_with(Vec::new)
+            .push(EventValue {
+                delay: Some(delay_ms),
+                data,
+            });
+        self
+    }
+
+    pub fn delay_ms(mut self, delay_ms: u64) -> Self {
+        self.current_entry().delay_ms = Some(delay_ms);
+        self
+    }
+
+    pub fn ttl_ms(mut self, ttl_ms: u64) -> Self {
+        self.current_entry().ttl_ms = Some(ttl_ms);
+        self
+    }
+
+    pub fn weight(mut self, weight: u32) -> Self {
+        self.current_entry().weight = Some(weight);
+        self
+    }
+
+    pub fn match_any_params(mut self, match_any_params: bool) -> Self {
+        self.current_entry().match_any_params = match_any_params;
+        self
+    }
+
+    pub fn build(self) -> MockData {
+        let mut mock_data = MockData::new();
+        for (method, entry) in self.entries {
+            mock_data.entry(method).or_default().push(entry);
+        }
+        mock_data
+    }
+
+    fn new_entry(&mut self) -> &mut ParamResponse {
+        let method = self
+            .method
+            .clone()
+            .expect("MockDataBuilder::method must be called before adding entries");
+        self.entries.push((method, ParamResponse::default()));
+        &mut self.entries.last_mut().unwrap().1
+    }
+
+    fn current_entry(&mut self) -> &mut ParamResponse {
+        if !matches!(self.entries.last(), Some((method, _)) if Some(method) == self.method.as_ref())
+        {
+            return self.new_entry();
+        }
+        &mut self.entries.last_mut().unwrap().1
+    }
+
+    fn response_entry(&mut self) -> &mut ParamResponse {
+        let entry = self.current_entry();
+        if entry.result.is_none() && entry.error.is_none() {
+            return &mut self.entries.last_mut().unwrap().1;
+        }
+        self.new_entry()
+    }
 }
 
 #[derive(Debug)]
@@ -43,7 +215,31 @@ pub struct ResponseSink {
 }
 
 impl ParamResponse {
+    /// Starts the TTL of the entry, if it has one, from now.
+    pub fn start_ttl(&mut self) {
+        if self.ttl_ms.is_some() {
+            self.added_at = Some(Instant::now());
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match (self.ttl_ms, self.added_at) {
+            (Some(ttl_ms), Some(added_at)) => added_at.elapsed() >= Duration::from_millis(ttl_ms),
+            _ => false,
+        }
+    }
+
     pub fn get_key(&self, key: &Value) -> Option<Self> {
+        if let Some(predicates) = &self.predicates {
+            debug!(
+                "get_key check predicates {:?} against {:?}",
+                predicates, key
+            );
+            return predicates
+                .iter()
+                .all(|predicate| predicate.matches(key))
+                .then(|| self.clone());
+        }
         match &self.params {
             Some(v) => {
                 debug!("get_key check {:?}={:?}", v, key);
@@ -52,9 +248,20 @@ impl ParamResponse {
                 }
                 None
             }
-            None => Some(self.clone()),
+            // An entry without params is meant for calls without params, unless it matches any
+            None if self.match_any_params => Some(self.clone()),
+            None => Self::is_empty_params(key).then(|| self.clone()),
         }
     }
+
+    fn is_empty_params(params: &Value) -> bool {
+        match params {
+            Value::Null => true,
+            Value::Object(map) => map.is_empty(),
+            _ => false,
+        }
+    }
+
     pub fn get_notification_id(&self) -> Option<String> {
         if let Some(params) = &self.params {
             if let Some(event) = params.get("event") {
@@ -70,21 +277,26 @@ impl ParamResponse {
         None
     }
 
+    /// Returns the responses to send for a request, in the order they must be delivered: the
+    /// result or error first, then the events in the order they are stored in the entry. The
+    /// sinks are kept in a `Vec` so multi-response sequences are sent in the same order on
+    /// every run.
     pub fn get_all(
         &self,
         id: Option<u64>,
         thunder_response: Option<ThunderRegisterParams>,
     ) -> Vec<ResponseSink> {
         let mut sink_responses = Vec::new();
+        let delay = self.delay_ms.unwrap_or(0);
         if let Some(e) = self.error.clone() {
             sink_responses.push(ResponseSink {
-                delay: 0,
-                data: json!({"jsonrpc": "2.0", "id": id, "error": e}),
+                delay,
+                data: json!(JsonRpcApiResponse::error(id, e)),
             });
         } else if let Some(v) = self.result.clone() {
             sink_responses.push(ResponseSink {
-                delay: 0,
-                data: json!({"jsonrpc": "2.0", "id": id, "result": v}),
+                delay,
+                data: json!(JsonRpcApiResponse::success(id, v)),
             });
 
             if let Some(events) = &self.events {
@@ -104,8 +316,8 @@ impl ParamResponse {
             }
         } else {
             sink_responses.push(ResponseSink {
-                delay: 0,
-                data: json!({"jsonrpc": "2.0", "id": id, "result": null}),
+                delay,
+                data: json!(JsonRpcApiResponse::success(id, Value::Null)),
             });
         }
         debug!("Total sink responses {:?}", sink_responses);
@@ -128,6 +340,8 @@ pub enum MockDataError {
     MissingRequestField,
     MissingResponseField,
     FailedToCreateKey(Value),
+    NoMatchingResponse(String),
+    ResultAndError(String),
 }
 
 impl std::error::Error for MockDataError {}
@@ -144,6 +358,12 @@ impl Display for MockDataError {
             Self::MissingRequestField => "The request field is missing.".to_owned(),
             Self::MissingResponseField => "The response field is missing.".to_owned(),
             Self::NotAnObject => "Payload must be an object.".to_owned(),
+            Self::NoMatchingResponse(method) => {
+                format!("No existing response to update for method {method}.")
+            }
+            Self::ResultAndError(method) => {
+                format!("A response for method {method} can't have both a result and an error.")
+            }
         };
 
         f.write_str(msg.as_str())
@@ -213,13 +433,34 @@ mod tests {
             result: None,
             error: None,
             events: None,
+            match_any_params: true,
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
             params: None,
         };
         assert!(response.get_key(&Value::Null).is_some());
+        assert!(response.get_key(&json!({"a": 1})).is_some());
+        let response = ParamResponse {
+            match_any_params: false,
+            ..response
+        };
+        assert!(response.get_key(&Value::Null).is_some());
+        assert!(response.get_key(&json!({})).is_some());
+        assert!(response.get_key(&json!({"a": 1})).is_none());
+        assert!(response.get_key(&json!([1])).is_none());
         let response = ParamResponse {
             result: None,
             error: None,
             events: None,
+            match_any_params: true,
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
             params: Some(Value::String("Some".to_owned())),
         };
         assert!(response.get_key(&Value::Null).is_none());
@@ -234,6 +475,12 @@ mod tests {
             result: None,
             error: None,
             events: None,
+            match_any_params: true,
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
             params: None,
         };
         assert!(response.get_notification_id().is_none());
@@ -241,6 +488,12 @@ mod tests {
             result: None,
             error: None,
             events: None,
+            match_any_params: true,
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
             params: Some(Value::String("Some".to_owned())),
         };
         assert!(response.get_notification_id().is_none());
@@ -249,6 +502,12 @@ mod tests {
             result: None,
             error: None,
             events: None,
+            match_any_params: true,
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
             params: Some(json!({
                 "event": "SomeEvent",
                 "id": "SomeId"
@@ -261,12 +520,33 @@ mod tests {
             .eq("SomeId.SomeEvent"));
     }
 
+    #[test]
+    fn test_param_response_ttl() {
+        let mut response: ParamResponse =
+            serde_json::from_value(json!({"result": 1, "ttl_ms": 50})).unwrap();
+        assert!(!response.is_expired());
+        response.start_ttl();
+        assert!(!response.is_expired());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(response.is_expired());
+
+        let mut response: ParamResponse = serde_json::from_value(json!({"result": 1})).unwrap();
+        response.start_ttl();
+        assert!(!response.is_expired());
+    }
+
     #[test]
     fn test_get_all() {
         let pr = ParamResponse {
             result: None,
             error: Some(json!({"code": -32010, "message": "Error Message"})),
             events: None,
+            match_any_params: true,
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
             params: None,
         };
         let response = pr.get_all(Some(0), None)[0]
@@ -287,6 +567,12 @@ mod tests {
                 data: json!({"event": 0}),
             }]),
             params: None,
+            match_any_params: true,
+            predicates: None,
+            ttl_ms: None,
+            added_at: None,
+            delay_ms: None,
+            weight: None,
         };
 
         let response = pr.get_all(Some(0), None)[0]
@@ -319,4 +605,73 @@ mod tests {
             panic!("Failure in get all with thunder register params")
         }
     }
+
+    #[test]
+    fn test_get_all_keeps_stored_order() {
+        let pr = ParamResponse {
+            result: Some(json!("ack")),
+            events: Some(vec![
+                EventValue {
+                    delay: Some(20),
+                    data: json!("first"),
+                },
+                EventValue {
+                    delay: Some(0),
+                    data: json!("second"),
+                },
+            ]),
+            ..Default::default()
+        };
+
+        for _ in 0..100 {
+            let sinks = pr.get_all(Some(1), None);
+            let order: Vec<Value> = sinks
+                .iter()
+                .map(|sink| {
+                    sink.data
+                        .get("result")
+                        .or_else(|| sink.data.get("params"))
+                        .cloned()
+                        .unwrap()
+                })
+                .collect();
+            assert_eq!(order, vec![json!("ack"), json!("first"), json!("second")]);
+            assert_eq!(
+                sinks.iter().map(|sink| sink.delay).collect::<Vec<u64>>(),
+                vec![0, 20, 0]
+            );
+        }
+    }
+
+    #[test]
+    fn test_mock_data_builder() {
+        let mock_data = MockDataBuilder::new()
+            .method("org.rdk.System.1.getFriendlyName")
+            .when_params(json!({"verbose": true}))
+            .respond(json!("Living Room TV"))
+            .delay_ms(50)
+            .when_params(json!({"verbose": false}))
+            .respond(json!("Living Room"))
+            .method("org.rdk.System.1.getMode")
+            .respond(json!("NORMAL"))
+            .event(json!({"mode": "EAS"}), 10)
+            .respond(json!("EAS"))
+            .match_any_params(false)
+            .build();
+
+        let entries = &mock_data["org.rdk.System.1.getFriendlyName"];
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].params, Some(json!({"verbose": true})));
+        assert_eq!(entries[0].delay_ms, Some(50));
+        assert_eq!(entries[1].result, Some(json!("Living Room")));
+        assert_eq!(entries[1].delay_ms, None);
+        assert_eq!(entries[0].get_all(Some(1), None)[0].delay, 50);
+
+        let entries = &mock_data["org.rdk.System.1.getMode"];
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].events.as_ref().map(Vec::len), Some(1));
+        assert!(entries[0].match_any_params);
+        assert_eq!(entries[1].result, Some(json!("EAS")));
+        assert!(!entries[1].match_any_params);
+    }
 }
@@ -15,12 +15,15 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 use crate::{
     mock_data::MockData,
     mock_device_ffi::EXTN_NAME,
-    mock_server::{EmitEventParams, MockServerRequest},
+    mock_server::{
+        AddRequestResponsesParams, EmitEventParams, HistoryParams, MockServerRequest,
+        RemoveMethodParams, ResetParams, ServerSelector, SnapshotParams, UnexpectedRequestsParams,
+    },
 };
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use ripple_sdk::{
@@ -31,10 +34,17 @@ use ripple_sdk::{
         extn_id::{ExtnClassId, ExtnId, ExtnProviderRequest, ExtnProviderResponse},
     },
     log::debug,
-    tokio::runtime::Runtime,
+    tokio::{
+        self,
+        runtime::{Handle, Runtime},
+    },
     utils::{error::RippleError, rpc_utils::rpc_err},
 };
 
+const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_REQUEST_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF_MS: u64 = 100;
+
 #[derive(Debug, Clone)]
 enum MockDeviceControllerError {
     RequestFailed(RippleError),
@@ -70,55 +80,152 @@ pub trait MockDeviceController {
     async fn emit_event(
         &self,
         ctx: CallContext,
-        req: EmitEventParams,
+        req: ServerSelector<EmitEventParams>,
     ) -> RpcResult<ExtnProviderResponse>;
 
     #[method(name = "mockdevice.addRequests")]
     async fn add_request_responses(
         &self,
         ctx: CallContext,
-        req: MockData,
+        req: ServerSelector<MockData>,
+    ) -> RpcResult<ExtnProviderResponse>;
+
+    #[method(name = "mockdevice.addRequestsBatch")]
+    async fn add_request_responses_batch(
+        &self,
+        ctx: CallContext,
+        req: AddRequestResponsesParams,
     ) -> RpcResult<ExtnProviderResponse>;
 
     #[method(name = "mockdevice.removeRequests")]
     async fn remove_requests(
         &self,
         ctx: CallContext,
-        req: MockData,
+        req: ServerSelector<MockData>,
+    ) -> RpcResult<ExtnProviderResponse>;
+
+    #[method(name = "mockdevice.removeMethod")]
+    async fn remove_method(
+        &self,
+        ctx: CallContext,
+        req: ServerSelector<RemoveMethodParams>,
+    ) -> RpcResult<ExtnProviderResponse>;
+
+    #[method(name = "mockdevice.updateRequestResponse")]
+    async fn update_request_response(
+        &self,
+        ctx: CallContext,
+        req: ServerSelector<MockData>,
+    ) -> RpcResult<ExtnProviderResponse>;
+
+    #[method(name = "mockdevice.reset")]
+    async fn reset(
+        &self,
+        ctx: CallContext,
+        req: ServerSelector<ResetParams>,
+    ) -> RpcResult<ExtnProviderResponse>;
+
+    #[method(name = "mockdevice.snapshot")]
+    async fn snapshot(
+        &self,
+        ctx: CallContext,
+        req: ServerSelector<SnapshotParams>,
+    ) -> RpcResult<ExtnProviderResponse>;
+
+    #[method(name = "mockdevice.unexpectedRequests")]
+    async fn unexpected_requests(
+        &self,
+        ctx: CallContext,
+        req: ServerSelector<UnexpectedRequestsParams>,
+    ) -> RpcResult<ExtnProviderResponse>;
+
+    #[method(name = "mockdevice.history")]
+    async fn history(
+        &self,
+        ctx: CallContext,
+        req: ServerSelector<HistoryParams>,
     ) -> RpcResult<ExtnProviderResponse>;
 }
 
 pub struct MockDeviceController {
     client: ExtnClient,
-    rt: Runtime,
+    handle: Handle,
+    // Only set when the controller owns its runtime, keeps it alive for the handle
+    _rt: Option<Runtime>,
     id: ExtnId,
+    request_timeout_ms: u64,
+    request_attempts: u32,
 }
 
 impl MockDeviceController {
+    /// Creates a controller with its own runtime, for standalone use outside of any runtime.
     pub fn new(client: ExtnClient) -> MockDeviceController {
+        let rt = Runtime::new().unwrap();
+        MockDeviceController {
+            client,
+            handle: rt.handle().clone(),
+            _rt: Some(rt),
+            id: ExtnId::new_channel(ExtnClassId::Device, EXTN_NAME.into()),
+            request_timeout_ms: DEFAULT_REQUEST_TIMEOUT_MS,
+            request_attempts: DEFAULT_REQUEST_ATTEMPTS,
+        }
+    }
+
+    /// Creates a controller which spawns its requests on an existing runtime.
+    pub fn new_with_handle(client: ExtnClient, handle: Handle) -> MockDeviceController {
         MockDeviceController {
             client,
-            rt: Runtime::new().unwrap(),
+            handle,
+            _rt: None,
             id: ExtnId::new_channel(ExtnClassId::Device, EXTN_NAME.into()),
+            request_timeout_ms: DEFAULT_REQUEST_TIMEOUT_MS,
+            request_attempts: DEFAULT_REQUEST_ATTEMPTS,
         }
     }
 
+    /// Overrides how long a request waits for the mock device extension to respond.
+    pub fn with_request_timeout(mut self, timeout_ms: u64) -> MockDeviceController {
+        self.request_timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Overrides how many times a request is attempted when it cannot be sent to the mock device
+    /// extension. Requests that were sent are never retried, even when they fail or time out.
+    pub fn with_request_attempts(mut self, attempts: u32) -> MockDeviceController {
+        self.request_attempts = attempts.max(1);
+        self
+    }
+
     async fn request(
         &self,
         request: MockServerRequest,
     ) -> Result<ExtnProviderResponse, MockDeviceControllerError> {
         debug!("request={request:?}");
         let client = self.client.clone();
+        let timeout_ms = self.request_timeout_ms;
+        let attempts = self.request_attempts;
         let request = ExtnProviderRequest {
             value: serde_json::to_value(request).unwrap(),
             id: self.id.clone(),
         };
-        self.rt
+        self.handle
             .spawn(async move {
-                client
-                    .standalone_request(request, 5000)
-                    .await
-                    .map_err(MockDeviceControllerError::RequestFailed)
+                let mut attempt = 1;
+                loop {
+                    let res: Result<ExtnProviderResponse, RippleError> =
+                        client.standalone_request(request.clone(), timeout_ms).await;
+                    match res {
+                        Err(RippleError::SendFailure) if attempt < attempts => {
+                            debug!("request could not be sent, attempt {attempt} of {attempts}");
+                            tokio::time::sleep(Duration::from_millis(
+                                RETRY_BACKOFF_MS * attempt as u64,
+                            ))
+                            .await;
+                            attempt += 1;
+                        }
+                        res => return res.map_err(MockDeviceControllerError::RequestFailed),
+                    }
+                }
             })
             .await
             .map_err(|_| MockDeviceControllerError::ExtnCommunicationFailed)?
@@ -130,7 +237,7 @@ impl MockDeviceControllerServer for MockDeviceController {
     async fn add_request_responses(
         &self,
         _ctx: CallContext,
-        req: MockData,
+        req: ServerSelector<MockData>,
     ) -> RpcResult<ExtnProviderResponse> {
         let res = self
             .request(MockServerRequest::AddRequestResponse(req))
@@ -140,10 +247,23 @@ impl MockDeviceControllerServer for MockDeviceController {
         Ok(res)
     }
 
+    async fn add_request_responses_batch(
+        &self,
+        _ctx: CallContext,
+        req: AddRequestResponsesParams,
+    ) -> RpcResult<ExtnProviderResponse> {
+        let res = self
+            .request(MockServerRequest::AddRequestResponses(req))
+            .await
+            .map_err(rpc_err)?;
+
+        Ok(res)
+    }
+
     async fn remove_requests(
         &self,
         _ctx: CallContext,
-        req: MockData,
+        req: ServerSelector<MockData>,
     ) -> RpcResult<ExtnProviderResponse> {
         let res = self
             .request(MockServerRequest::RemoveRequestResponse(req))
@@ -153,10 +273,36 @@ impl MockDeviceControllerServer for MockDeviceController {
         Ok(res)
     }
 
+    async fn remove_method(
+        &self,
+        _ctx: CallContext,
+        req: ServerSelector<RemoveMethodParams>,
+    ) -> RpcResult<ExtnProviderResponse> {
+        let res = self
+            .request(MockServerRequest::RemoveMethod(req))
+            .await
+            .map_err(rpc_err)?;
+
+        Ok(res)
+    }
+
+    async fn update_request_response(
+        &self,
+        _ctx: CallContext,
+        req: ServerSelector<MockData>,
+    ) -> RpcResult<ExtnProviderResponse> {
+        let res = self
+            .request(MockServerRequest::UpdateRequestResponse(req))
+            .await
+            .map_err(rpc_err)?;
+
+        Ok(res)
+    }
+
     async fn emit_event(
         &self,
         _ctx: CallContext,
-        req: EmitEventParams,
+        req: ServerSelector<EmitEventParams>,
     ) -> RpcResult<ExtnProviderResponse> {
         let res = self
             .request(MockServerRequest::EmitEvent(req))
@@ -165,4 +311,207 @@ impl MockDeviceControllerServer for MockDeviceController {
 
         Ok(res)
     }
+
+    async fn reset(
+        &self,
+        _ctx: CallContext,
+        req: ServerSelector<ResetParams>,
+    ) -> RpcResult<ExtnProviderResponse> {
+        let res = self
+            .request(MockServerRequest::Reset(req))
+            .await
+            .map_err(rpc_err)?;
+
+        Ok(res)
+    }
+
+    async fn snapshot(
+        &self,
+        _ctx: CallContext,
+        req: ServerSelector<SnapshotParams>,
+    ) -> RpcResult<ExtnProviderResponse> {
+        let res = self
+            .request(MockServerRequest::Snapshot(req))
+            .await
+            .map_err(rpc_err)?;
+
+        Ok(res)
+    }
+
+    async fn unexpected_requests(
+        &self,
+        _ctx: CallContext,
+        req: ServerSelector<UnexpectedRequestsParams>,
+    ) -> RpcResult<ExtnProviderResponse> {
+        let res = self
+            .request(MockServerRequest::UnexpectedRequests(req))
+            .await
+            .map_err(rpc_err)?;
+
+        Ok(res)
+    }
+
+    async fn history(
+        &self,
+        _ctx: CallContext,
+        req: ServerSelector<HistoryParams>,
+    ) -> RpcResult<ExtnProviderResponse> {
+        let res = self
+            .request(MockServerRequest::History(req))
+            .await
+            .map_err(rpc_err)?;
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{AddRequestResponseResponse, EmitEventResponse, MockServerResponse};
+    use ripple_sdk::{
+        async_channel::{bounded, unbounded, Receiver},
+        extn::{
+            client::extn_sender::ExtnSender,
+            extn_client_message::{ExtnMessage, ExtnResponse},
+            ffi::ffi_message::CExtnMessage,
+        },
+        tokio,
+    };
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    fn controller_on_current_runtime() -> (MockDeviceController, Receiver<CExtnMessage>) {
+        let (tx, rx) = unbounded();
+        let sender = ExtnSender::new(
+            tx,
+            ExtnId::get_main_target("main".into()),
+            vec![],
+            vec![],
+            None,
+        );
+        let (_, client_rx) = unbounded();
+        let controller = MockDeviceController::new_with_handle(
+            ExtnClient::new(client_rx, sender),
+            Handle::current(),
+        );
+        (controller, rx)
+    }
+
+    fn respond_to_requests(rx: Receiver<CExtnMessage>, response: MockServerResponse) {
+        respond_to_requests_after(rx, response, Duration::ZERO)
+    }
+
+    fn respond_to_requests_after(
+        rx: Receiver<CExtnMessage>,
+        response: MockServerResponse,
+        delay: Duration,
+    ) {
+        tokio::spawn(async move {
+            while let Ok(c_message) = rx.recv().await {
+                tokio::time::sleep(delay).await;
+                let callback = c_message.callback.clone().unwrap();
+                let message: ExtnMessage = c_message.try_into().unwrap();
+                let message = message
+                    .get_response(ExtnResponse::Value(
+                        serde_json::to_value(response.clone()).unwrap(),
+                    ))
+                    .unwrap();
+                // the caller may have already timed out and dropped the callback
+                let _ = callback.try_send(message.into());
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_add_request_response_on_current_runtime() {
+        let (controller, rx) = controller_on_current_runtime();
+        let expected = MockServerResponse::AddRequestResponse(AddRequestResponseResponse {
+            success: true,
+            error: None,
+        });
+        respond_to_requests(rx, expected.clone());
+
+        let res = controller
+            .add_request_responses(CallContext::mock(), MockData::new().into())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_value::<MockServerResponse>(res.value).unwrap(),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let response = MockServerResponse::EmitEvent(EmitEventResponse { success: true });
+        let params: ServerSelector<EmitEventParams> = serde_json::from_value(serde_json::json!({
+            "event": {"body": {}, "delay": 0}
+        }))
+        .unwrap();
+
+        let (controller, rx) = controller_on_current_runtime();
+        let controller = controller.with_request_timeout(2000);
+        respond_to_requests_after(rx, response.clone(), Duration::from_secs(1));
+        assert!(controller
+            .emit_event(CallContext::mock(), params.clone())
+            .await
+            .is_ok());
+
+        let (controller, rx) = controller_on_current_runtime();
+        let controller = controller.with_request_timeout(500);
+        respond_to_requests_after(rx, response, Duration::from_secs(1));
+        assert!(controller
+            .emit_event(CallContext::mock(), params)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_retried_after_send_failure() {
+        // A full channel to main makes the first attempt fail to send
+        let (tx, rx) = bounded(1);
+        let sender = ExtnSender::new(
+            tx.clone(),
+            ExtnId::get_main_target("main".into()),
+            vec![],
+            vec![],
+            None,
+        );
+        let (_, client_rx) = unbounded();
+        let controller = MockDeviceController::new_with_handle(
+            ExtnClient::new(client_rx, sender),
+            Handle::current(),
+        );
+        tx.try_send(CExtnMessage {
+            id: "blocker".to_owned(),
+            requestor: String::new(),
+            target: String::new(),
+            target_id: String::new(),
+            payload: String::new(),
+            callback: None,
+            ts: 0,
+        })
+        .unwrap();
+
+        let expected = MockServerResponse::AddRequestResponse(AddRequestResponseResponse {
+            success: true,
+            error: None,
+        });
+        let response = expected.clone();
+        tokio::spawn(async move {
+            // Only make room once the first attempt has failed
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let _ = rx.recv().await;
+            respond_to_requests(rx, response);
+        });
+
+        let res = controller
+            .add_request_responses(CallContext::mock(), MockData::new().into())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_value::<MockServerResponse>(res.value).unwrap(),
+            expected
+        );
+    }
 }
@@ -39,8 +39,8 @@ use ripple_sdk::{
 
 use crate::{
     mock_device_controller::{MockDeviceController, MockDeviceControllerServer},
-    mock_device_processor::MockDeviceProcessor,
-    utils::boot_ws_server,
+    mock_device_processor::{MockDeviceProcessor, MockServers},
+    utils::{boot_named_ws_servers, boot_ws_server},
 };
 
 pub const EXTN_NAME: &str = "mock_device";
@@ -80,12 +80,15 @@ fn start_launcher(sender: ExtnSender, receiver: CReceiver<CExtnMessage>) {
     runtime.block_on(async move {
         let client_c = client.clone();
         tokio::spawn(async move {
-            match boot_ws_server(client.clone()).await {
-                Ok(server) => {
-                    client.add_request_processor(MockDeviceProcessor::new(client.clone(), server))
-                }
+            let servers = match boot_ws_server(client.clone()).await {
+                Ok(server) => MockServers::new(server),
                 Err(err) => panic!("websocket server failed to start. {}", err),
             };
+            let servers = match boot_named_ws_servers(&client).await {
+                Ok(named) => servers.with_named(named),
+                Err(err) => panic!("named websocket servers failed to start. {}", err),
+            };
+            client.add_request_processor(MockDeviceProcessor::new(client.clone(), servers));
 
             // Lets Main know that the mock_device channel is ready
             let _ = client.event(ExtnStatus::Ready);
@@ -31,26 +31,57 @@ use ripple_sdk::{
     tokio::sync::mpsc::{Receiver, Sender},
     utils::error::RippleError,
 };
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     mock_device_ffi::EXTN_NAME,
     mock_server::{
-        AddRequestResponseResponse, EmitEventResponse, MockServerRequest, MockServerResponse,
-        RemoveRequestResponse,
+        AddRequestResponseResponse, AddRequestResponsesResponse, EmitEventParams,
+        EmitEventResponse, HistoryResponse, MockServerRequest, MockServerResponse,
+        RemoveMethodResponse, RemoveRequestResponse, ResetResponse, SnapshotResponse,
+        UnexpectedRequestsResponse, UpdateRequestResponse,
     },
     mock_web_socket_server::MockWebSocketServer,
 };
 
+/// The mock servers managed by the extension, the default server plus any named servers.
+#[derive(Debug, Clone)]
+pub struct MockServers {
+    default: Arc<MockWebSocketServer>,
+    named: HashMap<String, Arc<MockWebSocketServer>>,
+}
+
+impl MockServers {
+    pub fn new(default: Arc<MockWebSocketServer>) -> Self {
+        Self {
+            default,
+            named: HashMap::new(),
+        }
+    }
+
+    pub fn with_named(mut self, named: HashMap<String, Arc<MockWebSocketServer>>) -> Self {
+        self.named.extend(named);
+        self
+    }
+
+    /// Returns the named server, or the default server when no name is given.
+    pub fn get(&self, name: Option<&str>) -> Option<Arc<MockWebSocketServer>> {
+        match name {
+            Some(name) => self.named.get(name).cloned(),
+            None => Some(self.default.clone()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MockDeviceState {
     client: ExtnClient,
-    server: Arc<MockWebSocketServer>,
+    servers: MockServers,
 }
 
 impl MockDeviceState {
-    fn new(client: ExtnClient, server: Arc<MockWebSocketServer>) -> Self {
-        Self { client, server }
+    fn new(client: ExtnClient, servers: MockServers) -> Self {
+        Self { client, servers }
     }
 }
 
@@ -60,9 +91,9 @@ pub struct MockDeviceProcessor {
 }
 
 impl MockDeviceProcessor {
-    pub fn new(client: ExtnClient, server: Arc<MockWebSocketServer>) -> MockDeviceProcessor {
+    pub fn new(client: ExtnClient, servers: MockServers) -> MockDeviceProcessor {
         MockDeviceProcessor {
-            state: MockDeviceState::new(client, server),
+            state: MockDeviceState::new(client, servers),
             streamer: DefaultExtnStreamer::new(),
         }
     }
@@ -84,6 +115,125 @@ impl MockDeviceProcessor {
             }
         }
     }
+
+    async fn handle_request(
+        server: Arc<MockWebSocketServer>,
+        message: MockServerRequest,
+    ) -> MockServerResponse {
+        match message {
+            MockServerRequest::AddRequestResponse(params) => {
+                let resp = match server.add_request_response_v2(params.params).await {
+                    Ok(_) => AddRequestResponseResponse {
+                        success: true,
+                        error: None,
+                    },
+                    Err(err) => AddRequestResponseResponse {
+                        success: false,
+                        error: Some(err.to_string()),
+                    },
+                };
+                MockServerResponse::AddRequestResponse(resp)
+            }
+            MockServerRequest::AddRequestResponses(params) => {
+                let resp = match server
+                    .add_request_responses_v2(params.into_requests())
+                    .await
+                {
+                    Ok(added) => AddRequestResponsesResponse {
+                        success: true,
+                        added,
+                        error: None,
+                        failed_index: None,
+                    },
+                    Err((index, err)) => AddRequestResponsesResponse {
+                        success: false,
+                        added: 0,
+                        error: Some(err.to_string()),
+                        failed_index: Some(index),
+                    },
+                };
+                MockServerResponse::AddRequestResponses(resp)
+            }
+            MockServerRequest::RemoveRequestResponse(params) => {
+                let resp = match server.remove_request_response_v2(params.params).await {
+                    Ok(_) => RemoveRequestResponse {
+                        success: true,
+                        error: None,
+                    },
+                    Err(err) => RemoveRequestResponse {
+                        success: false,
+                        error: Some(err.to_string()),
+                    },
+                };
+                MockServerResponse::RemoveRequestResponse(resp)
+            }
+            MockServerRequest::UpdateRequestResponse(params) => {
+                let resp = match server.update_request_response_v2(params.params).await {
+                    Ok(_) => UpdateRequestResponse {
+                        success: true,
+                        error: None,
+                    },
+                    Err(err) => UpdateRequestResponse {
+                        success: false,
+                        error: Some(err.to_string()),
+                    },
+                };
+                MockServerResponse::UpdateRequestResponse(resp)
+            }
+            MockServerRequest::RemoveMethod(params) => {
+                let removed = server.remove_method(&params.params.method).await;
+                MockServerResponse::RemoveMethod(RemoveMethodResponse {
+                    success: true,
+                    removed,
+                })
+            }
+            MockServerRequest::Reset(params) => {
+                let cleared = server.reset(params.params.disconnect_peers).await;
+                MockServerResponse::Reset(ResetResponse {
+                    success: true,
+                    cleared,
+                })
+            }
+            MockServerRequest::Snapshot(params) => {
+                let data = serde_json::to_value(server.snapshot()).unwrap_or_default();
+                let error = params.params.path.and_then(|path| {
+                    std::fs::write(
+                        &path,
+                        serde_json::to_string_pretty(&data).unwrap_or_default(),
+                    )
+                    .err()
+                    .map(|e| format!("Failed to write snapshot to {path}. Error: {e}"))
+                });
+                MockServerResponse::Snapshot(SnapshotResponse {
+                    success: error.is_none(),
+                    data,
+                    error,
+                })
+            }
+            MockServerRequest::UnexpectedRequests(_) => {
+                MockServerResponse::UnexpectedRequests(UnexpectedRequestsResponse {
+                    success: true,
+                    requests: server.take_unexpected_requests(),
+                })
+            }
+            MockServerRequest::History(_) => MockServerResponse::History(HistoryResponse {
+                success: true,
+                entries: server.history(),
+            }),
+            MockServerRequest::EmitEvent(params) => {
+                let EmitEventParams { event, method } = params.params;
+                match method {
+                    Some(method) => {
+                        server
+                            .emit_notification(&method, &event.body, event.delay)
+                            .await;
+                    }
+                    None => server.emit_event(&event.body, event.delay).await,
+                }
+                MockServerResponse::EmitEvent(EmitEventResponse { success: true })
+            }
+        }
+    }
 }
 
 impl ExtnStreamProcessor for MockDeviceProcessor {
@@ -121,71 +271,129 @@ impl ExtnRequestProcessor for MockDeviceProcessor {
         extracted_message: Self::VALUE,
     ) -> bool {
         debug!("extn_request={extn_request:?}, extracted_message={extracted_message:?}");
-        if let Ok(message) = serde_json::from_value::<MockServerRequest>(extracted_message.value) {
-            match message {
-                MockServerRequest::AddRequestResponse(params) => {
-                    let resp = match state.server.add_request_response_v2(params).await {
-                        Ok(_) => AddRequestResponseResponse {
-                            success: true,
-                            error: None,
-                        },
-                        Err(err) => AddRequestResponseResponse {
-                            success: false,
-                            error: Some(err.to_string()),
-                        },
-                    };
-                    Self::respond(
-                        state.client.clone(),
-                        extn_request,
-                        MockServerResponse::AddRequestResponse(resp),
-                    )
-                    .await
-                }
-                MockServerRequest::RemoveRequestResponse(params) => {
-                    let resp = match state.server.remove_request_response_v2(params).await {
-                        Ok(_) => RemoveRequestResponse {
-                            success: true,
-                            error: None,
-                        },
-                        Err(err) => RemoveRequestResponse {
-                            success: false,
-                            error: Some(err.to_string()),
-                        },
-                    };
-                    Self::respond(
-                        state.client.clone(),
-                        extn_request,
-                        MockServerResponse::RemoveRequestResponse(resp),
-                    )
-                    .await
-                }
-                MockServerRequest::EmitEvent(params) => {
-                    state
-                        .server
-                        .emit_event(&params.event.body, params.event.delay)
-                        .await;
-
-                    Self::respond(
-                        state.client.clone(),
-                        extn_request,
-                        MockServerResponse::EmitEvent(EmitEventResponse { success: true }),
-                    )
-                    .await
-                }
-            }
-        } else {
-            Self::handle_error(state.client, extn_request, RippleError::ProcessorError).await
-        }
+        let Ok(message) = serde_json::from_value::<MockServerRequest>(extracted_message.value)
+        else {
+            return Self::handle_error(state.client, extn_request, RippleError::ProcessorError)
+                .await;
+        };
+        let Some(server) = state.servers.get(message.server()) else {
+            error!("Unknown mock server {:?}", message.server());
+            return Self::handle_error(state.client, extn_request, RippleError::NotAvailable).await;
+        };
+        let resp = Self::handle_request(server, message).await;
+        Self::respond(state.client, extn_request, resp).await
     }
 }
 
 #[cfg(test)]
 mod tests {
-    #[test]
-    #[should_panic]
-    fn test_add_request_response() {
-        todo!(
-            "currently unable to test this without a testing solution so ExtnClient interactions"
+    use super::*;
+    use crate::{
+        mock_config::MockConfig,
+        mock_data::MockData,
+        mock_server::{ResetParams, ServerSelector},
+        mock_web_socket_server::WsServerParameters,
+    };
+    use ripple_sdk::{tokio, utils::mock_utils::MockExtnChannel};
+
+    async fn server() -> Arc<MockWebSocketServer> {
+        MockWebSocketServer::new(
+            MockData::new(),
+            WsServerParameters::default(),
+            MockConfig::default(),
+        )
+        .await
+        .expect("Unable to start server")
+        .into_arc()
+    }
+
+    fn add_request(server: &str) -> MockServerRequest {
+        MockServerRequest::AddRequestResponse(ServerSelector {
+            server: Some(server.to_owned()),
+            params: serde_json::from_value(serde_json::json!({
+                "org.rdk.System.1.getFriendlyName": [{"result": server}]
+            }))
+            .unwrap(),
+        })
+    }
+
+    fn reset_request(server: Option<&str>) -> MockServerRequest {
+        MockServerRequest::Reset(ServerSelector {
+            server: server.map(str::to_owned),
+            params: ResetParams::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_routed_to_named_servers() {
+        let servers = MockServers::new(server().await).with_named(HashMap::from([
+            ("first".to_owned(), server().await),
+            ("second".to_owned(), server().await),
+        ]));
+        assert!(servers.get(Some("third")).is_none());
+
+        for name in ["first", "second", "second"] {
+            let message = add_request(name);
+            let server = servers.get(message.server()).unwrap();
+            MockDeviceProcessor::handle_request(server, message).await;
+        }
+
+        for (name, expected) in [(Some("first"), 1), (Some("second"), 1), (None, 0)] {
+            let message = reset_request(name);
+            let server = servers.get(message.server()).unwrap();
+            assert_eq!(
+                MockDeviceProcessor::handle_request(server, message).await,
+                MockServerResponse::Reset(ResetResponse {
+                    success: true,
+                    cleared: expected,
+                })
+            );
+        }
+    }
+
+    fn provider_request(message: MockServerRequest) -> ExtnProviderRequest {
+        ExtnProviderRequest {
+            value: serde_json::to_value(message).unwrap(),
+            id: ExtnId::new_channel(ExtnClassId::Device, EXTN_NAME.into()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_request_response() {
+        let channel =
+            MockExtnChannel::new(ExtnId::new_channel(ExtnClassId::Device, EXTN_NAME.into()));
+        let servers = MockServers::new(server().await);
+        let state = MockDeviceState::new(channel.client(), servers.clone());
+
+        let request = provider_request(MockServerRequest::AddRequestResponse(ServerSelector {
+            server: None,
+            params: serde_json::from_value(serde_json::json!({
+                "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+            }))
+            .unwrap(),
+        }));
+        let message = channel.request(request.clone());
+        assert!(MockDeviceProcessor::process_request(state.clone(), message, request).await);
+        assert_eq!(
+            channel.recv_response().await,
+            Some(ExtnResponse::Value(
+                serde_json::to_value(MockServerResponse::AddRequestResponse(
+                    AddRequestResponseResponse {
+                        success: true,
+                        error: None,
+                    }
+                ))
+                .unwrap()
+            ))
+        );
+        assert_eq!(servers.get(None).unwrap().reset(false).await, 1);
+
+        let request = provider_request(reset_request(Some("missing")));
+        let message = channel.request(request.clone());
+        assert!(!MockDeviceProcessor::process_request(state, message, request).await);
+        assert_eq!(
+            channel.recv_response().await,
+            Some(ExtnResponse::Error(RippleError::NotAvailable))
         );
     }
 }
@@ -0,0 +1,235 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+
+/// A single assertion on request params, written as `<path> [<op> <json>]`.
+///
+/// Supported forms:
+/// - `$.a.b` passes when the path exists
+/// - `$.a.b == <json>` / `$.a.b != <json>` compare the value at the path
+/// - `$.a.b in [<json>, ...]` passes when the value at the path is one of the listed values
+///
+/// Path segments are object keys, or array indexes when the segment is a number.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ParamPredicate {
+    source: String,
+    path: Vec<String>,
+    op: PredicateOp,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PredicateOp {
+    Exists,
+    Eq(Value),
+    Ne(Value),
+    In(Vec<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredicateParseError {
+    predicate: String,
+    reason: String,
+}
+
+impl std::error::Error for PredicateParseError {}
+
+impl Display for PredicateParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid predicate \"{}\": {}",
+            self.predicate, self.reason
+        )
+    }
+}
+
+impl ParamPredicate {
+    pub fn parse(source: &str) -> Result<Self, PredicateParseError> {
+        let error = |reason: &str| PredicateParseError {
+            predicate: source.to_owned(),
+            reason: reason.to_owned(),
+        };
+        let trimmed = source.trim();
+        let (path, rest) = match trimmed.split_once(char::is_whitespace) {
+            Some((path, rest)) => (path, rest.trim()),
+            None => (trimmed, ""),
+        };
+        let path = Self::parse_path(path).ok_or_else(|| error("path must start with \"$\""))?;
+
+        let op = if rest.is_empty() {
+            PredicateOp::Exists
+        } else {
+            let (op, value) = ["==", "!=", "in"]
+                .into_iter()
+                .find_map(|op| rest.strip_prefix(op).map(|value| (op, value.trim())))
+                .ok_or_else(|| error("expected one of ==, != or in"))?;
+            let value: Value =
+                serde_json::from_str(value).map_err(|e| error(&format!("bad value, {e}")))?;
+            match (op, value) {
+                ("==", value) => PredicateOp::Eq(value),
+                ("!=", value) => PredicateOp::Ne(value),
+                (_, Value::Array(values)) => PredicateOp::In(values),
+                _ => return Err(error("in expects an array")),
+            }
+        };
+
+        Ok(Self {
+            source: source.to_owned(),
+            path,
+            op,
+        })
+    }
+
+    fn parse_path(path: &str) -> Option<Vec<String>> {
+        let segments = path.strip_prefix('$')?;
+        if segments.is_empty() {
+            return Some(Vec::new());
+        }
+        let segments = segments.strip_prefix('.')?;
+        let segments: Vec<String> = segments.split('.').map(str::to_owned).collect();
+        if segments.iter().any(String::is_empty) {
+            return None;
+        }
+        Some(segments)
+    }
+
+    fn resolve<'a>(&self, params: &'a Value) -> Option<&'a Value> {
+        self.path
+            .iter()
+            .try_fold(params, |value, segment| match value {
+                Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+                _ => value.get(segment),
+            })
+    }
+
+    pub fn matches(&self, params: &Value) -> bool {
+        let value = self.resolve(params);
+        match &self.op {
+            PredicateOp::Exists => value.is_some(),
+            PredicateOp::Eq(expected) => value == Some(expected),
+            PredicateOp::Ne(expected) => value != Some(expected),
+            PredicateOp::In(values) => value.is_some_and(|v| values.contains(v)),
+        }
+    }
+}
+
+impl TryFrom<String> for ParamPredicate {
+    type Error = PredicateParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<ParamPredicate> for String {
+    fn from(value: ParamPredicate) -> Self {
+        value.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params() -> Value {
+        json!({
+            "autoplay": true,
+            "metadata": {"rating": "PG", "tags": ["kids", "new"]}
+        })
+    }
+
+    #[test]
+    fn test_equality_predicates() {
+        let params = params();
+        assert!(ParamPredicate::parse("$.autoplay == true")
+            .unwrap()
+            .matches(&params));
+        assert!(ParamPredicate::parse("$.metadata.rating == \"PG\"")
+            .unwrap()
+            .matches(&params));
+        assert!(ParamPredicate::parse("$.metadata.tags.1 == \"new\"")
+            .unwrap()
+            .matches(&params));
+        assert!(!ParamPredicate::parse("$.autoplay == false")
+            .unwrap()
+            .matches(&params));
+        assert!(ParamPredicate::parse("$.autoplay != false")
+            .unwrap()
+            .matches(&params));
+    }
+
+    #[test]
+    fn test_membership_predicates() {
+        let params = params();
+        assert!(
+            ParamPredicate::parse("$.metadata.rating in [\"PG\", \"G\"]")
+                .unwrap()
+                .matches(&params)
+        );
+        assert!(!ParamPredicate::parse("$.metadata.rating in [\"R\"]")
+            .unwrap()
+            .matches(&params));
+        assert!(!ParamPredicate::parse("$.missing in [null]")
+            .unwrap()
+            .matches(&params));
+    }
+
+    #[test]
+    fn test_existence_predicates() {
+        let params = params();
+        assert!(ParamPredicate::parse("$.metadata.tags")
+            .unwrap()
+            .matches(&params));
+        assert!(ParamPredicate::parse("$").unwrap().matches(&params));
+        assert!(!ParamPredicate::parse("$.metadata.year")
+            .unwrap()
+            .matches(&params));
+        assert!(!ParamPredicate::parse("$.autoplay.value")
+            .unwrap()
+            .matches(&params));
+    }
+
+    #[test]
+    fn test_invalid_predicates() {
+        for source in [
+            "autoplay == true",
+            "$.",
+            "$.a..b",
+            "$.a > 1",
+            "$.a == tru",
+            "$.a in \"PG\"",
+        ] {
+            assert!(ParamPredicate::parse(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_predicate_serde_round_trip() {
+        let predicates: Vec<ParamPredicate> =
+            serde_json::from_value(json!(["$.autoplay == true", "$.metadata"])).unwrap();
+        assert_eq!(
+            serde_json::to_value(&predicates).unwrap(),
+            json!(["$.autoplay == true", "$.metadata"])
+        );
+        assert!(serde_json::from_value::<ParamPredicate>(json!("autoplay")).is_err());
+    }
+}
@@ -92,16 +92,94 @@ pub struct EventPayload {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum MockServerRequest {
-    EmitEvent(EmitEventParams),
-    AddRequestResponse(MockData),
-    RemoveRequestResponse(MockData),
+    EmitEvent(ServerSelector<EmitEventParams>),
+    AddRequestResponse(ServerSelector<MockData>),
+    AddRequestResponses(AddRequestResponsesParams),
+    RemoveRequestResponse(ServerSelector<MockData>),
+    RemoveMethod(ServerSelector<RemoveMethodParams>),
+    UpdateRequestResponse(ServerSelector<MockData>),
+    Reset(ServerSelector<ResetParams>),
+    Snapshot(ServerSelector<SnapshotParams>),
+    UnexpectedRequests(ServerSelector<UnexpectedRequestsParams>),
+    History(ServerSelector<HistoryParams>),
+}
+
+impl MockServerRequest {
+    /// Name of the mock server the request is for, `None` for the default server
+    pub fn server(&self) -> Option<&str> {
+        match self {
+            Self::EmitEvent(params) => params.server.as_deref(),
+            Self::AddRequestResponse(params)
+            | Self::RemoveRequestResponse(params)
+            | Self::UpdateRequestResponse(params) => params.server.as_deref(),
+            Self::AddRequestResponses(params) => params.server(),
+            Self::RemoveMethod(params) => params.server.as_deref(),
+            Self::Reset(params) => params.server.as_deref(),
+            Self::Snapshot(params) => params.server.as_deref(),
+            Self::UnexpectedRequests(params) => params.server.as_deref(),
+            Self::History(params) => params.server.as_deref(),
+        }
+    }
+}
+
+/// Request params with an optional selector for a named mock server.
+/// The selector sits next to the params, e.g. `{"server": "cloud", "<method>": [...]}`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerSelector<T> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(flatten)]
+    pub params: T,
+}
+
+impl<T> From<T> for ServerSelector<T> {
+    fn from(params: T) -> Self {
+        Self {
+            server: None,
+            params,
+        }
+    }
+}
+
+/// Params of a batch add, either a plain list for the default server or the list along with
+/// the name of the mock server.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum AddRequestResponsesParams {
+    Default(Vec<MockData>),
+    Selected {
+        server: Option<String>,
+        requests: Vec<MockData>,
+    },
+}
+
+impl AddRequestResponsesParams {
+    pub fn server(&self) -> Option<&str> {
+        match self {
+            Self::Default(_) => None,
+            Self::Selected { server, .. } => server.as_deref(),
+        }
+    }
+
+    pub fn into_requests(self) -> Vec<MockData> {
+        match self {
+            Self::Default(requests) | Self::Selected { requests, .. } => requests,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum MockServerResponse {
     AddRequestResponse(AddRequestResponseResponse),
+    AddRequestResponses(AddRequestResponsesResponse),
     EmitEvent(EmitEventResponse),
     RemoveRequestResponse(RemoveRequestResponse),
+    RemoveMethod(RemoveMethodResponse),
+    UpdateRequestResponse(UpdateRequestResponse),
+    Reset(ResetResponse),
+    Snapshot(SnapshotResponse),
+    UnexpectedRequests(UnexpectedRequestsResponse),
+    History(HistoryResponse),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -116,6 +194,16 @@ pub struct AddRequestResponseResponse {
     pub error: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AddRequestResponsesResponse {
+    pub success: bool,
+    /// Number of entries added, zero when any entry failed
+    pub added: usize,
+    pub error: Option<String>,
+    /// Index of the entry which failed, nothing is added in that case
+    pub failed_index: Option<usize>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RemoveRequestParams {
     pub request: MessagePayload,
@@ -127,11 +215,111 @@ pub struct RemoveRequestResponse {
     pub error: Option<String>,
 }
 
-// TODO: add a clear all mock data request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoveMethodParams {
+    /// Method whose responses are all removed, regardless of their params
+    pub method: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RemoveMethodResponse {
+    pub success: bool,
+    /// Number of responses which were removed
+    pub removed: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UpdateRequestResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ResetParams {
+    /// Also close the connections of every connected peer
+    #[serde(default)]
+    pub disconnect_peers: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResetResponse {
+    pub success: bool,
+    /// Number of mock data entries which were removed
+    pub cleared: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SnapshotParams {
+    /// Also write the snapshot to this file
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SnapshotResponse {
+    pub success: bool,
+    /// The mock data in the same shape as the mock data file
+    pub data: Value,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UnexpectedRequestsParams {}
+
+/// A request which didn't match any mock while the server is in strict mode
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UnexpectedRequest {
+    pub method: String,
+    pub params: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UnexpectedRequestsResponse {
+    pub success: bool,
+    /// Requests recorded since the last call, the list is cleared once returned
+    pub requests: Vec<UnexpectedRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HistoryParams {}
+
+/// A request processed by the mock server
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub method: String,
+    pub params: Option<Value>,
+    /// Whether a mocked response was found for the request
+    pub matched: bool,
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+/// Counters of a mock server, answered on its health endpoint
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ServerStats {
+    pub port: u16,
+    pub connected_peers: usize,
+    /// Number of methods with mock data
+    pub mocked_methods: usize,
+    /// Number of requests kept in the history
+    pub history_entries: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HistoryResponse {
+    pub success: bool,
+    /// The most recent requests, oldest first
+    pub entries: Vec<HistoryEntry>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EmitEventParams {
     pub event: EventPayload,
+    /// When set, the body is sent as the params of a notification to the peers subscribed to
+    /// this method, e.g. `org.rdk.DisplaySettings.1.resolutionChanged`. Otherwise the body is
+    /// broadcast as is to every peer.
+    #[serde(default)]
+    pub method: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -172,4 +360,75 @@ mod tests {
         assert_eq!(PayloadType::JsonRpc.to_string(), "jsonrpc".to_owned());
         assert_eq!(String::from(&PayloadType::JsonRpc), "jsonrpc".to_owned());
     }
+
+    #[test]
+    fn test_mock_server_request_round_trip() {
+        // The controller serializes requests and the processor deserializes the same enum
+        let mock_data: MockData = serde_json::from_value(serde_json::json!({
+            "org.rdk.System.1.getSystemVersions": [{"result": {"stbVersion": "1"}}]
+        }))
+        .unwrap();
+        let event = EmitEventParams {
+            event: EventPayload {
+                body: serde_json::json!({"method": "event"}),
+                delay: 10,
+            },
+            method: None,
+        };
+        for request in [
+            MockServerRequest::EmitEvent(event.into()),
+            MockServerRequest::AddRequestResponse(mock_data.clone().into()),
+            MockServerRequest::AddRequestResponses(AddRequestResponsesParams::Default(vec![
+                mock_data.clone(),
+            ])),
+            MockServerRequest::RemoveRequestResponse(mock_data.clone().into()),
+            MockServerRequest::RemoveMethod(
+                RemoveMethodParams {
+                    method: "org.rdk.System.1.getSystemVersions".to_owned(),
+                }
+                .into(),
+            ),
+            MockServerRequest::UpdateRequestResponse(ServerSelector {
+                server: Some("cloud".to_owned()),
+                params: mock_data,
+            }),
+            MockServerRequest::Reset(
+                ResetParams {
+                    disconnect_peers: true,
+                }
+                .into(),
+            ),
+            MockServerRequest::Snapshot(SnapshotParams::default().into()),
+            MockServerRequest::UnexpectedRequests(UnexpectedRequestsParams::default().into()),
+            MockServerRequest::History(HistoryParams::default().into()),
+        ] {
+            let value = serde_json::to_value(&request).unwrap();
+            let parsed: MockServerRequest = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(serde_json::to_value(parsed).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_server_selector() {
+        let request: ServerSelector<MockData> = serde_json::from_value(serde_json::json!({
+            "server": "cloud",
+            "org.rdk.System.1.getSystemVersions": [{"result": {"stbVersion": "1"}}]
+        }))
+        .unwrap();
+        assert_eq!(request.server.as_deref(), Some("cloud"));
+        assert_eq!(request.params.len(), 1);
+
+        let batch: AddRequestResponsesParams = serde_json::from_value(serde_json::json!([
+            {"org.rdk.System.1.getSystemVersions": [{"result": {"stbVersion": "1"}}]}
+        ]))
+        .unwrap();
+        assert_eq!(batch.server(), None);
+        let batch: AddRequestResponsesParams = serde_json::from_value(serde_json::json!({
+            "server": "cloud",
+            "requests": [{"org.rdk.System.1.getSystemVersions": [{"result": {}}]}]
+        }))
+        .unwrap();
+        assert_eq!(batch.server(), Some("cloud"));
+        assert_eq!(batch.into_requests().len(), 1);
+    }
 }
@@ -15,19 +15,21 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     net::SocketAddr,
-    sync::{Arc, RwLock},
-    time::Duration,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use http::{HeaderMap, StatusCode};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use ripple_sdk::{
-    api::gateway::rpc_gateway_api::JsonRpcApiRequest,
+    api::gateway::rpc_gateway_api::{JsonRpcApiRequest, JsonRpcApiResponse},
     futures::{stream::SplitSink, SinkExt, StreamExt},
     log::{debug, error, warn},
     tokio::{
         self,
+        io::{AsyncReadExt, AsyncWriteExt},
         net::{TcpListener, TcpStream},
         sync::Mutex,
     },
@@ -42,11 +44,38 @@ use tokio_tungstenite::{
 
 use crate::{
     errors::MockServerWebSocketError,
-    mock_config::MockConfig,
+    mock_config::{MockConfig, QueryMatchMode},
     mock_data::{MockData, MockDataError, ParamResponse, ResponseSink},
+    mock_server::{HistoryEntry, ServerStats, UnexpectedRequest},
     utils::is_value_jsonrpc,
 };
 
+const INVALID_REQUEST_CODE: i32 = -32600;
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+const INVALID_PARAMS_CODE: i32 = -32602;
+
+/// Why no mocked response could be found for a request
+#[derive(Debug, Clone, PartialEq)]
+enum MockLookupError {
+    /// There is no mock data for the method
+    MethodNotFound,
+    /// The method is mocked but none of its entries match the params
+    ParamsNotMatched,
+}
+
+impl MockLookupError {
+    fn to_error(&self) -> Value {
+        match self {
+            Self::MethodNotFound => {
+                json!({"code": METHOD_NOT_FOUND_CODE, "message": "Method not found"})
+            }
+            Self::ParamsNotMatched => {
+                json!({"code": INVALID_PARAMS_CODE, "message": "Invalid params"})
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ThunderRegisterParams {
     pub event: String,
@@ -120,6 +149,16 @@ pub struct MockWebSocketServer {
     connected_peer_sinks: WSConnection,
 
     config: MockConfig,
+
+    unexpected_requests: Arc<std::sync::Mutex<Vec<UnexpectedRequest>>>,
+
+    history: Arc<std::sync::Mutex<VecDeque<HistoryEntry>>>,
+
+    /// Notification method, keyed by peer, of the subscribers of each event method
+    subscriptions: Arc<std::sync::Mutex<HashMap<String, HashMap<String, String>>>>,
+
+    /// Picks among weighted responses
+    rng: std::sync::Mutex<SmallRng>,
 }
 
 impl MockWebSocketServer {
@@ -134,6 +173,8 @@ impl MockWebSocketServer {
             .map_err(|_| MockServerWebSocketError::CantListen)?
             .port();
 
+        let rng = Self::create_rng(config.rng_seed);
+
         Ok(Self {
             listener,
             port,
@@ -142,15 +183,24 @@ impl MockWebSocketServer {
             conn_query_params: server_config.query_params.unwrap_or_default(),
             connected_peer_sinks: Arc::new(Mutex::new(HashMap::new())),
             config,
-            mock_data_v2: Arc::new(RwLock::new(
-                mock_data_v2
-                    .into_iter()
-                    .map(|(k, v)| (k.to_lowercase(), v))
-                    .collect(),
-            )),
+            unexpected_requests: Arc::new(std::sync::Mutex::new(Vec::new())),
+            history: Arc::new(std::sync::Mutex::new(VecDeque::new())),
+            subscriptions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            rng: std::sync::Mutex::new(rng),
+            mock_data_v2: Arc::new(RwLock::new(Self::prepare_mock_data(mock_data_v2))),
         })
     }
 
+    fn create_rng(seed: Option<u64>) -> SmallRng {
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        SmallRng::seed_from_u64(seed)
+    }
+
     pub fn port(&self) -> u16 {
         self.port
     }
@@ -194,7 +244,12 @@ impl MockWebSocketServer {
         }
     }
 
-    async fn handle_connection(&self, peer: SocketAddr, stream: TcpStream) -> Result<()> {
+    async fn handle_connection(&self, peer: SocketAddr, mut stream: TcpStream) -> Result<()> {
+        if self.config.enable_health && Self::is_health_request(&stream).await {
+            debug!("Health request peer={peer}");
+            return self.respond_health(&mut stream).await.map_err(Error::Io);
+        }
+
         let callback = |request: &handshake::client::Request,
                         mut response: handshake::server::Response| {
             let path = request.uri().path();
@@ -211,12 +266,25 @@ impl MockWebSocketServer {
                 debug!("Connection response {:?}", response);
             }
 
+            if let Some(token) = &self.config.required_bearer_token {
+                let expected = format!("Bearer {token}");
+                let authorization = request.headers().get(http::header::AUTHORIZATION);
+                if authorization.map(|v| v.as_bytes()) != Some(expected.as_bytes()) {
+                    *response.status_mut() = StatusCode::UNAUTHORIZED;
+                    error!("Missing or invalid bearer token in the Authorization header");
+                    debug!("Connection response {:?}", response);
+                }
+            }
+
             let request_query =
                 url::form_urlencoded::parse(request.uri().query().unwrap_or("").as_bytes())
                     .into_owned()
                     .collect::<HashMap<String, String>>();
 
-            let eq_num_params = self.conn_query_params.len() == request_query.len();
+            let eq_num_params = match self.config.query_match_mode {
+                QueryMatchMode::Exact => self.conn_query_params.len() == request_query.len(),
+                QueryMatchMode::Subset => true,
+            };
             let all_params_match =
                 self.conn_query_params
                     .iter()
@@ -230,11 +298,31 @@ impl MockWebSocketServer {
                 debug!("Connection response {:?}", response);
             }
 
+            // Answering with an error response makes the handshake fail, rather than upgrading
+            // the connection after a rejection
+            if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+                let reason = response.status().canonical_reason().unwrap_or_default();
+                let mut error_response = handshake::server::ErrorResponse::new(Some(reason.into()));
+                *error_response.status_mut() = response.status();
+                return Err(error_response);
+            }
+
             Ok(response)
         };
-        let ws_stream = accept_hdr_async(stream, callback)
-            .await
-            .expect("Failed to accept");
+        let ws_stream = match accept_hdr_async(stream, callback).await {
+            Ok(ws_stream) => ws_stream,
+            Err(Error::Http(response)) => {
+                debug!(
+                    "Refused connection peer={peer} status={}",
+                    response.status()
+                );
+                return Ok(());
+            }
+            Err(err) => {
+                debug!("Handshake failed peer={peer}");
+                return Err(err);
+            }
+        };
 
         let (send, mut recv) = ws_stream.split();
 
@@ -244,7 +332,14 @@ impl MockWebSocketServer {
 
         while let Some(msg) = recv.next().await {
             debug!("incoming message");
-            let msg = msg?;
+            let msg = match msg {
+                Ok(msg) => msg,
+                Err(e) => {
+                    // the peer went away without closing, forget it like a closed one
+                    self.remove_connected_peer(&peer).await;
+                    return Err(e);
+                }
+            };
             debug!("Message: {:?}", msg);
 
             if msg.is_close() {
@@ -263,6 +358,7 @@ impl MockWebSocketServer {
 
                 debug!("Parsed message: {:?}", request_message);
 
+                self.track_subscription(&peer, &request_message);
                 let responses = match self.find_responses(request_message).await {
                     Some(value) => value,
                     None => continue,
@@ -310,39 +406,55 @@ impl MockWebSocketServer {
     }
 
     async fn find_responses(&self, request_message: Value) -> Option<Vec<ResponseSink>> {
-        debug!(
-            "is value json rpc {} {}",
-            request_message,
-            is_value_jsonrpc(&request_message)
-        );
+        if !is_value_jsonrpc(&request_message) {
+            error!("Invalid json rpc request {:?}", request_message);
+            let id = request_message.get("id").cloned().unwrap_or(Value::Null);
+            return Some(vec![ResponseSink {
+                delay: 0,
+                data: json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": INVALID_REQUEST_CODE, "message": "Invalid Request"}
+                }),
+            }]);
+        }
         if let Ok(request) = serde_json::from_value::<JsonRpcApiRequest>(request_message.clone()) {
             if let Some(id) = request.id {
-                debug!("{}", self.config.activate_all_plugins);
-                if self.config.activate_all_plugins
-                    && request.method.contains("Controller.1.status")
-                {
+                if let Some(state) = self.plugin_state(&request.method) {
+                    self.record_request(&request, true);
                     return Some(vec![ResponseSink {
                         delay: 0,
-                        data: json!({"jsonrpc": "2.0", "id": id, "result": [{"state": "activated"}]}),
+                        data: json!(JsonRpcApiResponse::success(
+                            Some(id),
+                            json!([{ "state": state }])
+                        )),
                     }]);
-                } else if let Some(v) = self.responses_for_key_v2(&request) {
-                    if v.events.is_some() {
-                        if let Some(params) = request.params {
-                            if let Ok(t) =
-                                serde_json::from_value::<ThunderRegisterParams>(params.clone())
-                            {
-                                return Some(v.get_all(Some(id), Some(t)));
+                }
+                let lookup = self.responses_for_key_v2(&request);
+                self.record_request(&request, lookup.is_ok());
+                match lookup {
+                    Ok(v) => {
+                        if v.events.is_some() {
+                            if let Some(params) = request.params {
+                                if let Ok(t) =
+                                    serde_json::from_value::<ThunderRegisterParams>(params.clone())
+                                {
+                                    return Some(v.get_all(Some(id), Some(t)));
+                                }
                             }
                         }
+                        return Some(v.get_all(Some(id), None));
+                    }
+                    Err(err) => {
+                        return Some(vec![ResponseSink {
+                            delay: 0,
+                            data: json!(JsonRpcApiResponse::error(Some(id), err.to_error())),
+                        }]);
                     }
-                    return Some(v.get_all(Some(id), None));
                 }
-                return Some(vec![ResponseSink {
-                    delay: 0,
-                    data: json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32001, "message":"not found"}}),
-                }]);
             } else {
-                error!("Failed to get id from request {:?}", request_message);
+                // Notifications must not be answered
+                debug!("Received notification {:?}", request_message);
             }
         } else {
             error!(
@@ -354,25 +466,180 @@ impl MockWebSocketServer {
         None
     }
 
-    fn responses_for_key_v2(&self, req: &JsonRpcApiRequest) -> Option<ParamResponse> {
-        let mock_data = self.mock_data_v2.read().unwrap();
-        if let Some(v) = mock_data.get(&req.method.to_lowercase()).cloned() {
-            if v.len() == 1 {
-                return v.first().cloned();
-            } else if let Some(params) = &req.params {
-                let mut new_params = params.clone();
-                if req.method.ends_with(".register") {
-                    if let Some(v) = params.get("event").cloned() {
-                        new_params = json!({"event": v})
-                    }
-                }
-                for response in v {
-                    if response.get_key(&new_params).is_some() {
-                        return Some(response);
-                    }
-                }
+    /// Returns the state answered to a `Controller.1.status@<callsign>` request, `None` lets the
+    /// request go through the mocks.
+    fn plugin_state(&self, method: &str) -> Option<String> {
+        if !method.contains("Controller.1.status") {
+            return None;
+        }
+        let configured = method
+            .split_once('@')
+            .and_then(|(_, callsign)| self.config.plugin_states.get(callsign));
+        if let Some(state) = configured {
+            return Some(state.clone());
+        }
+        debug!("{}", self.config.activate_all_plugins);
+        self.config
+            .activate_all_plugins
+            .then(|| "activated".to_owned())
+    }
+
+    /// Adds the request to the history and, in strict mode, to the unexpected requests when
+    /// it didn't match any mock.
+    fn record_request(&self, req: &JsonRpcApiRequest, matched: bool) {
+        if !matched && self.config.strict {
+            self.unexpected_requests
+                .lock()
+                .unwrap()
+                .push(UnexpectedRequest {
+                    method: req.method.clone(),
+                    params: req.params.clone(),
+                });
+        }
+        if self.config.history_size == 0 {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut history = self.history.lock().unwrap();
+        if history.len() == self.config.history_size {
+            history.pop_front();
+        }
+        history.push_back(HistoryEntry {
+            method: req.method.clone(),
+            params: req.params.clone(),
+            matched,
+            timestamp,
+        });
+    }
+
+    pub async fn stats(&self) -> ServerStats {
+        ServerStats {
+            port: self.port,
+            connected_peers: self.connected_peer_sinks.lock().await.len(),
+            mocked_methods: self.mock_data().len(),
+            history_entries: self.history.lock().unwrap().len(),
+        }
+    }
+
+    /// Whether the connection starts with a plain HTTP `GET /healthz` rather than a WebSocket
+    /// upgrade. The request is only peeked, so the handshake still sees it otherwise.
+    async fn is_health_request(stream: &TcpStream) -> bool {
+        let mut buf = [0; 1024];
+        let n = match stream.peek(&mut buf).await {
+            Ok(n) => n,
+            Err(_) => return false,
+        };
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let mut lines = request.lines();
+        let is_health = lines
+            .next()
+            .map(|line| line.starts_with("GET /healthz ") || line.starts_with("GET /healthz?"))
+            .unwrap_or(false);
+        is_health
+            && !lines.any(|line| {
+                line.to_ascii_lowercase()
+                    .trim_start()
+                    .starts_with("upgrade:")
+            })
+    }
+
+    async fn respond_health(&self, stream: &mut TcpStream) -> std::io::Result<()> {
+        // Drain the request so closing the socket doesn't reset the connection
+        let mut buf = [0; 1024];
+        let _ = stream.read(&mut buf).await?;
+        let body = json!(self.stats().await).to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    /// Returns the most recent requests processed by the server, oldest first.
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Read access to the mock data. A panic while the lock was held can't leave the map half
+    /// updated, so a poisoned lock is recovered rather than failing every later request.
+    fn mock_data(&self) -> RwLockReadGuard<'_, MockData> {
+        self.mock_data_v2.read().unwrap_or_else(|poisoned| {
+            warn!("Recovering mock data lock poisoned by a panic");
+            poisoned.into_inner()
+        })
+    }
+
+    /// Write access to the mock data, recovering a poisoned lock like [Self::mock_data].
+    fn mock_data_mut(&self) -> RwLockWriteGuard<'_, MockData> {
+        self.mock_data_v2.write().unwrap_or_else(|poisoned| {
+            warn!("Recovering mock data lock poisoned by a panic");
+            poisoned.into_inner()
+        })
+    }
+
+    fn responses_for_key_v2(
+        &self,
+        req: &JsonRpcApiRequest,
+    ) -> Result<ParamResponse, MockLookupError> {
+        let mock_data = self.mock_data();
+        let v: Vec<ParamResponse> = mock_data
+            .get(&req.method.to_lowercase())
+            .ok_or(MockLookupError::MethodNotFound)?
+            .iter()
+            .filter(|response| !response.is_expired())
+            .cloned()
+            .collect();
+        if v.is_empty() {
+            return Err(MockLookupError::MethodNotFound);
+        }
+        if let [response] = v.as_slice() {
+            if response.match_any_params && response.predicates.is_none() {
+                return Ok(response.clone());
+            }
+        }
+        let params = req.params.clone().unwrap_or(Value::Null);
+        let mut new_params = params.clone();
+        if req.method.ends_with(".register") {
+            if let Some(v) = params.get("event").cloned() {
+                new_params = json!({"event": v})
             }
         }
+        let matching: Vec<ParamResponse> = v
+            .into_iter()
+            .filter(|response| response.get_key(&new_params).is_some())
+            .collect();
+        self.pick_response(matching)
+            .ok_or(MockLookupError::ParamsNotMatched)
+    }
+
+    /// Picks the first response, or a random one by weight when any of them has a weight.
+    fn pick_response(&self, mut responses: Vec<ParamResponse>) -> Option<ParamResponse> {
+        if !responses.iter().any(|response| response.weight.is_some()) {
+            return responses.into_iter().next();
+        }
+        let weights: Vec<u64> = responses
+            .iter()
+            .map(|response| response.weight.unwrap_or(1) as u64)
+            .collect();
+        let total: u64 = weights.iter().sum();
+        if total == 0 {
+            return responses.into_iter().next();
+        }
+        let mut pick = self
+            .rng
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .gen_range(0..total);
+        for (i, weight) in weights.into_iter().enumerate() {
+            if pick < weight {
+                return Some(responses.swap_remove(i));
+            }
+            pick -= weight;
+        }
         None
     }
 
@@ -388,20 +655,113 @@ impl MockWebSocketServer {
     async fn remove_connected_peer(&self, peer: &SocketAddr) {
         let mut peers = self.connected_peer_sinks.lock().await;
         let _ = peers.remove(&peer.to_string());
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, subscribers| {
+                subscribers.remove(&peer.to_string());
+                !subscribers.is_empty()
+            });
+    }
+
+    /// Keeps track of the Thunder `register` and `unregister` calls of the peer, so events can be
+    /// sent to the subscribers of a method with the id they registered with. Methods are matched
+    /// ignoring their casing, like the mock data.
+    fn track_subscription(&self, peer: &SocketAddr, request_message: &Value) {
+        let request = match serde_json::from_value::<JsonRpcApiRequest>(request_message.clone()) {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        let (callsign, register) = if let Some(c) = request.method.strip_suffix(".register") {
+            (c, true)
+        } else if let Some(c) = request.method.strip_suffix(".unregister") {
+            (c, false)
+        } else {
+            return;
+        };
+        let Some(params) = request
+            .params
+            .and_then(|p| serde_json::from_value::<ThunderRegisterParams>(p).ok())
+        else {
+            return;
+        };
+
+        let method = format!("{}.{}", callsign, params.event).to_lowercase();
+        let mut subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if register {
+            subscriptions
+                .entry(method)
+                .or_default()
+                .insert(peer.to_string(), format!("{}.{}", params.id, params.event));
+        } else if let Some(subscribers) = subscriptions.get_mut(&method) {
+            subscribers.remove(&peer.to_string());
+            if subscribers.is_empty() {
+                subscriptions.remove(&method);
+            }
+        }
     }
 
     pub async fn add_request_response_v2(&self, request: MockData) -> Result<(), MockDataError> {
-        let mut mock_data = self.mock_data_v2.write().unwrap();
-        let lower_key_mock_data: MockData = request
-            .into_iter()
-            .map(|(k, v)| (k.to_lowercase(), v))
-            .collect();
-        mock_data.extend(lower_key_mock_data);
+        let mut mock_data = self.mock_data_mut();
+        Self::insert_mock_data(&mut mock_data, request)
+    }
+
+    /// Adds every entry all-or-nothing. All entries are validated before any of them is added.
+    /// Returns the number of entries added or the index of the first invalid entry along with
+    /// its error.
+    pub async fn add_request_responses_v2(
+        &self,
+        requests: Vec<MockData>,
+    ) -> Result<usize, (usize, MockDataError)> {
+        for (index, request) in requests.iter().enumerate() {
+            Self::validate_mock_data(request).map_err(|err| (index, err))?;
+        }
+        let count = requests.len();
+        let mut mock_data = self.mock_data_mut();
+        for request in requests {
+            mock_data.extend(Self::prepare_mock_data(request));
+        }
+        Ok(count)
+    }
+
+    fn insert_mock_data(mock_data: &mut MockData, request: MockData) -> Result<(), MockDataError> {
+        Self::validate_mock_data(&request)?;
+        mock_data.extend(Self::prepare_mock_data(request));
         Ok(())
     }
 
+    /// Every method needs a name and a response can't be both a result and an error
+    fn validate_mock_data(mock_data: &MockData) -> Result<(), MockDataError> {
+        for (method, responses) in mock_data {
+            if method.trim().is_empty() {
+                return Err(MockDataError::MissingRequestField);
+            }
+            if responses
+                .iter()
+                .any(|response| response.result.is_some() && response.error.is_some())
+            {
+                return Err(MockDataError::ResultAndError(method.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lower cases the method names and starts the TTL of every entry
+    fn prepare_mock_data(mock_data: MockData) -> MockData {
+        mock_data
+            .into_iter()
+            .map(|(k, mut v)| {
+                v.iter_mut().for_each(ParamResponse::start_ttl);
+                (k.to_lowercase(), v)
+            })
+            .collect()
+    }
+
     pub async fn remove_request_response_v2(&self, request: MockData) -> Result<(), MockDataError> {
-        let mut mock_data = self.mock_data_v2.write().unwrap();
+        let mut mock_data = self.mock_data_mut();
         for (cleanup_key, cleanup_params) in request {
             if let Some(v) = mock_data.remove(&cleanup_key.to_lowercase()) {
                 let mut new_param_response = Vec::new();
@@ -431,6 +791,101 @@ impl MockWebSocketServer {
         Ok(())
     }
 
+    /// Removes every response for `method` whatever its params and returns how many were
+    /// removed. Use [MockWebSocketServer::remove_request_response_v2] to remove a single one.
+    pub async fn remove_method(&self, method: &str) -> usize {
+        let mut mock_data = self.mock_data_mut();
+        mock_data
+            .remove(&method.to_lowercase())
+            .map_or(0, |responses| responses.len())
+    }
+
+    /// Replaces existing responses in place under a single write lock, so concurrent requests
+    /// never see the method unmocked. Each entry replaces the response with the same `params`
+    /// and `predicates`. Nothing is updated when any entry has no matching response.
+    pub async fn update_request_response_v2(&self, request: MockData) -> Result<(), MockDataError> {
+        let mut mock_data = self.mock_data_mut();
+        let mut staged = mock_data.clone();
+        for (method, updates) in request {
+            let responses = staged
+                .get_mut(&method.to_lowercase())
+                .ok_or_else(|| MockDataError::NoMatchingResponse(method.clone()))?;
+            for update in updates {
+                let current = responses
+                    .iter_mut()
+                    .find(|r| r.params == update.params && r.predicates == update.predicates)
+                    .ok_or_else(|| MockDataError::NoMatchingResponse(method.clone()))?;
+                *current = update;
+                current.start_ttl();
+            }
+        }
+        *mock_data = staged;
+        Ok(())
+    }
+
+    /// Returns a copy of the current mock data in the same shape as the mock data file, so it
+    /// can be saved and loaded again.
+    pub fn snapshot(&self) -> MockData {
+        self.mock_data().clone()
+    }
+
+    /// Returns the requests which didn't match any mock since the last call and clears them.
+    /// Requests are only recorded when the server runs in strict mode.
+    pub fn take_unexpected_requests(&self) -> Vec<UnexpectedRequest> {
+        std::mem::take(&mut *self.unexpected_requests.lock().unwrap())
+    }
+
+    /// Removes all mock data, optionally closing every connected peer, and returns the
+    /// number of entries removed.
+    pub async fn reset(&self, disconnect_peers: bool) -> usize {
+        let cleared = {
+            let mut mock_data = self.mock_data_mut();
+            let cleared = mock_data.len();
+            mock_data.clear();
+            cleared
+        };
+        if disconnect_peers {
+            let mut peers = self.connected_peer_sinks.lock().await;
+            for (peer, mut sink) in peers.drain() {
+                if let Err(e) = sink.close().await {
+                    warn!("Error closing connection. peer={peer} err={e:?}");
+                }
+            }
+        }
+        cleared
+    }
+
+    /// Sends `params` as a notification to every peer subscribed to `method`, using the id the
+    /// peer registered with, and returns the number of peers it was sent to.
+    pub async fn emit_notification(&self, method: &str, params: &Value, delay: u64) -> usize {
+        let subscribers = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&method.to_lowercase())
+            .cloned()
+            .unwrap_or_default();
+        if delay > 0 {
+            tokio::time::sleep(Duration::from_millis(delay)).await
+        }
+        let mut peers = self.connected_peer_sinks.lock().await;
+        let mut sent = 0;
+        for (peer, notification_method) in subscribers {
+            let Some(sink) = peers.get_mut(&peer) else {
+                continue;
+            };
+            let notification =
+                json!({"jsonrpc": "2.0", "method": notification_method, "params": params});
+            if let Err(e) = sink.send(Message::Text(notification.to_string())).await {
+                error!("Error sending notification. peer={peer} err={e:?}");
+            } else {
+                debug!("sent notification. peer={peer} notification={notification:?}");
+                sent += 1;
+            }
+        }
+        sent
+    }
+
     pub async fn emit_event(self: Arc<Self>, event: &Value, delay: u64) {
         let mut peers = self.connected_peer_sinks.lock().await;
         let event_value = event.to_string();
@@ -455,6 +910,7 @@ impl MockWebSocketServer {
 #[cfg(test)]
 mod tests {
     use ripple_sdk::tokio::time::{self, error::Elapsed, Duration};
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 
     use super::*;
 
@@ -596,8 +1052,8 @@ mod tests {
             "id":1,
             "jsonrpc":"2.0".to_owned(),
             "error":{
-                "code":-32001,
-                "message":"not found".to_owned()
+                "code":-32601,
+                "message":"Method not found".to_owned()
             }
         });
         assert!(json_response_validator(&response, &expected));
@@ -618,4 +1074,1132 @@ mod tests {
         });
         assert!(json_response_validator(&response, &expected));
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_add_request_responses_batch() {
+        let server = start_server(HashMap::default()).await;
+
+        let results = server
+            .add_request_responses_v2(vec![
+                get_mock_data(json!({"org.rdk.System.1.getFriendlyName": [{"result": "first"}]})),
+                get_mock_data(json!({"org.rdk.System.1.getTimeZone": [{"result": "second"}]})),
+                get_mock_data(json!({"org.rdk.System.1.getMode": [{"result": "third"}]})),
+            ])
+            .await;
+        assert_eq!(results, Ok(3));
+
+        for (id, method, result) in [
+            (1, "org.rdk.System.1.getFriendlyName", "first"),
+            (2, "org.rdk.System.1.getTimeZone", "second"),
+            (3, "org.rdk.System.1.getMode", "third"),
+        ] {
+            let response = request_response_with_timeout(
+                server.clone(),
+                Message::Text(json!({"jsonrpc": "2.0", "id": id, "method": method}).to_string()),
+            )
+            .await
+            .expect("no response from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server response");
+
+            let expected = json!({"jsonrpc": "2.0", "id": id, "result": result});
+            assert!(json_response_validator(&response, &expected));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_add_request_responses_batch_rejects_invalid_entry() {
+        let server = start_server(HashMap::default()).await;
+
+        let results = server
+            .add_request_responses_v2(vec![
+                get_mock_data(json!({"org.rdk.System.1.getFriendlyName": [{"result": "first"}]})),
+                get_mock_data(json!({"org.rdk.System.1.getTimeZone": [{
+                    "result": "second",
+                    "error": {"code": -32010, "message": "Unavailable"}
+                }]})),
+                get_mock_data(json!({"org.rdk.System.1.getMode": [{"result": "third"}]})),
+            ])
+            .await;
+        assert_eq!(
+            results,
+            Err((
+                1,
+                MockDataError::ResultAndError("org.rdk.System.1.getTimeZone".to_owned())
+            ))
+        );
+        // none of the entries were added
+        assert!(server.snapshot().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reset() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "first"}],
+            "org.rdk.System.1.getMode": [{"result": "second"}]
+        })))
+        .await;
+
+        assert_eq!(server.reset(true).await, 2);
+
+        let response = request_response_with_timeout(
+            server.clone(),
+            Message::Text(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"})
+                    .to_string(),
+            ),
+        )
+        .await
+        .expect("no response from server within timeout")
+        .expect("connection to server was closed")
+        .expect("error in server response");
+
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": -32601, "message": "Method not found"}
+        });
+        assert!(json_response_validator(&response, &expected));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remove_method() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [
+                {"params": {"id": 1}, "result": "first"},
+                {"params": {"id": 2}, "result": "second"}
+            ],
+            "org.rdk.System.1.getMode": [{"result": "mode"}]
+        })))
+        .await;
+
+        assert_eq!(
+            server
+                .remove_method("org.rdk.System.1.getFriendlyName")
+                .await,
+            2
+        );
+        assert_eq!(
+            server
+                .remove_method("org.rdk.System.1.getFriendlyName")
+                .await,
+            0
+        );
+
+        for id in [1, 2] {
+            let req = JsonRpcApiRequest::new(
+                "org.rdk.System.1.getFriendlyName".to_owned(),
+                Some(json!({ "id": id })),
+            );
+            assert!(server.responses_for_key_v2(&req).is_err());
+        }
+        let req = JsonRpcApiRequest::new("org.rdk.System.1.getMode".to_owned(), None);
+        assert!(server.responses_for_key_v2(&req).is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_poisoned_mock_data_lock_is_recovered() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "first"}]
+        })))
+        .await;
+
+        let mock_data = server.mock_data_v2.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = mock_data.write().unwrap();
+            panic!("poisoning the mock data lock");
+        })
+        .join();
+        assert!(server.mock_data_v2.is_poisoned());
+
+        server
+            .add_request_response_v2(get_mock_data(json!({
+                "org.rdk.System.1.getMode": [{"result": "mode"}]
+            })))
+            .await
+            .unwrap();
+        for (id, method, result) in [
+            (1, "org.rdk.System.1.getFriendlyName", "first"),
+            (2, "org.rdk.System.1.getMode", "mode"),
+        ] {
+            let response = request_response_with_timeout(
+                server.clone(),
+                Message::Text(json!({"jsonrpc": "2.0", "id": id, "method": method}).to_string()),
+            )
+            .await
+            .expect("no response from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server response");
+
+            let expected = json!({"jsonrpc": "2.0", "id": id, "result": result});
+            assert!(json_response_validator(&response, &expected));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unmatched_params_are_invalid_params() {
+        let method = "org.rdk.DisplaySettings.1.getCurrentResolution";
+        let server = start_server(get_mock_data(json!({
+            method: [
+                {"params": {"videoDisplay": "HDMI0"}, "result": "2160p"},
+                {"params": {"videoDisplay": "HDMI1"}, "result": "1080p"}
+            ]
+        })))
+        .await;
+
+        let response = request_response_with_timeout(
+            server,
+            Message::Text(
+                json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": {"videoDisplay": "HDMI2"}})
+                    .to_string(),
+            ),
+        )
+        .await
+        .expect("no response from server within timeout")
+        .expect("connection to server was closed")
+        .expect("error in server response");
+
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": -32602, "message": "Invalid params"}
+        });
+        assert!(json_response_validator(&response, &expected));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_single_response_match_any_params() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getMode": [
+                {"params": {"mode": "NORMAL"}, "result": "any"}
+            ],
+            "org.rdk.System.1.getTimeZone": [
+                {"params": {"zone": "UTC"}, "result": "strict", "match_any_params": false}
+            ]
+        })))
+        .await;
+
+        for (method, params, expected) in [
+            (
+                "org.rdk.System.1.getMode",
+                json!({"mode": "EAS"}),
+                json!({"jsonrpc": "2.0", "id": 1, "result": "any"}),
+            ),
+            (
+                "org.rdk.System.1.getTimeZone",
+                json!({"zone": "UTC"}),
+                json!({"jsonrpc": "2.0", "id": 1, "result": "strict"}),
+            ),
+            (
+                "org.rdk.System.1.getTimeZone",
+                json!({"zone": "EST"}),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": {"code": -32602, "message": "Invalid params"}
+                }),
+            ),
+        ] {
+            let response = request_response_with_timeout(
+                server.clone(),
+                Message::Text(
+                    json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params})
+                        .to_string(),
+                ),
+            )
+            .await
+            .expect("no response from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server response");
+            assert!(json_response_validator(&response, &expected));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_method_casing_is_ignored() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+        })))
+        .await;
+
+        for method in [
+            "org.rdk.System.1.getFriendlyName",
+            "org.rdk.system.1.getfriendlyname",
+            "ORG.RDK.SYSTEM.1.GETFRIENDLYNAME",
+        ] {
+            let response = server
+                .find_responses(json!({"jsonrpc": "2.0", "id": 1, "method": method}))
+                .await
+                .unwrap();
+            assert_eq!(
+                response[0].data,
+                json!({"jsonrpc": "2.0", "id": 1, "result": "Living Room"})
+            );
+        }
+        let response = server
+            .find_responses(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getName"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response[0].data,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {"code": -32601, "message": "Method not found"}
+            })
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_nullary_responses() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [
+                {"params": {"verbose": true}, "result": "Living Room TV"},
+                {"result": "Living Room", "match_any_params": false}
+            ]
+        })))
+        .await;
+
+        let not_matched = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": -32602, "message": "Invalid params"}
+        });
+        for (params, expected) in [
+            (
+                None,
+                json!({"jsonrpc": "2.0", "id": 1, "result": "Living Room"}),
+            ),
+            (
+                Some(json!({})),
+                json!({"jsonrpc": "2.0", "id": 1, "result": "Living Room"}),
+            ),
+            (
+                Some(json!({"verbose": true})),
+                json!({"jsonrpc": "2.0", "id": 1, "result": "Living Room TV"}),
+            ),
+            (Some(json!({"verbose": false})), not_matched),
+        ] {
+            let mut request =
+                json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"});
+            if let Some(params) = params {
+                request["params"] = params;
+            }
+            let response = server.find_responses(request).await.unwrap();
+            assert_eq!(response[0].data, expected);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_predicate_responses() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.Player.1.play": [
+                {
+                    "predicates": ["$.autoplay == true", "$.metadata.rating in [\"PG\", \"G\"]"],
+                    "result": "family"
+                },
+                {"predicates": ["$.metadata.rating"], "result": "rated"}
+            ]
+        })))
+        .await;
+
+        for (params, expected) in [
+            (
+                json!({"autoplay": true, "metadata": {"rating": "G"}}),
+                json!({"jsonrpc": "2.0", "id": 1, "result": "family"}),
+            ),
+            (
+                json!({"autoplay": false, "metadata": {"rating": "G"}}),
+                json!({"jsonrpc": "2.0", "id": 1, "result": "rated"}),
+            ),
+            (
+                json!({"autoplay": true}),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": {"code": -32602, "message": "Invalid params"}
+                }),
+            ),
+        ] {
+            let response = request_response_with_timeout(
+                server.clone(),
+                Message::Text(
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": 1,
+                        "method": "org.rdk.Player.1.play",
+                        "params": params
+                    })
+                    .to_string(),
+                ),
+            )
+            .await
+            .expect("no response from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server response");
+            assert!(json_response_validator(&response, &expected));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_update_request_response() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"params": {"id": 1}, "result": "initial"}]
+        })))
+        .await;
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    let req = JsonRpcApiRequest::new(
+                        "org.rdk.System.1.getFriendlyName".to_owned(),
+                        Some(json!({"id": 1})),
+                    );
+                    for _ in 0..500 {
+                        assert!(server.responses_for_key_v2(&req).is_ok());
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..500 {
+            server
+                .update_request_response_v2(get_mock_data(json!({
+                    "org.rdk.System.1.getFriendlyName": [{"params": {"id": 1}, "result": i}]
+                })))
+                .await
+                .unwrap();
+            tokio::task::yield_now().await;
+        }
+        for reader in readers {
+            reader.await.expect("reader observed the method missing");
+        }
+
+        let req = JsonRpcApiRequest::new(
+            "org.rdk.System.1.getFriendlyName".to_owned(),
+            Some(json!({"id": 1})),
+        );
+        assert_eq!(
+            server
+                .responses_for_key_v2(&req)
+                .ok()
+                .and_then(|r| r.result),
+            Some(json!(499))
+        );
+        assert!(server
+            .update_request_response_v2(get_mock_data(json!({
+                "org.rdk.System.1.getFriendlyName": [{"params": {"id": 2}, "result": "other"}]
+            })))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_snapshot_round_trip() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}],
+            "org.rdk.Player.1.play": [
+                {"predicates": ["$.autoplay == true"], "result": "playing"},
+                {"params": {"autoplay": false}, "result": "paused", "match_any_params": false}
+            ]
+        })))
+        .await;
+        let requests = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "org.rdk.Player.1.play", "params": {"autoplay": true}}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "org.rdk.Player.1.play", "params": {"autoplay": false}}),
+        ];
+        let mut before = Vec::new();
+        for request in &requests {
+            before.push(
+                server.find_responses(request.clone()).await.unwrap()[0]
+                    .data
+                    .clone(),
+            );
+        }
+
+        let snapshot = serde_json::to_string(&server.snapshot()).unwrap();
+        server.reset(false).await;
+        server
+            .add_request_response_v2(serde_json::from_str(&snapshot).unwrap())
+            .await
+            .unwrap();
+
+        for (request, expected) in requests.iter().zip(before) {
+            let response = server.find_responses(request.clone()).await.unwrap();
+            assert_eq!(response[0].data, expected);
+        }
+        assert_eq!(
+            serde_json::to_value(server.snapshot()).unwrap(),
+            serde_json::from_str::<Value>(&snapshot).unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_invalid_jsonrpc_request() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+        })))
+        .await;
+
+        for (request, expected_id) in [
+            (json!({"jsonrpc": "2.0", "id": 1}), json!(1)),
+            (json!({"jsonrpc": "2.0", "id": 2, "method": 5}), json!(2)),
+            (
+                json!({"jsonrpc": "1.0", "id": 3, "method": "org.rdk.System.1.getFriendlyName"}),
+                json!(3),
+            ),
+            (
+                json!({"id": 4, "method": "org.rdk.System.1.getFriendlyName"}),
+                json!(4),
+            ),
+            (
+                json!({"jsonrpc": "1.0", "method": "org.rdk.System.1.getFriendlyName"}),
+                Value::Null,
+            ),
+        ] {
+            let response =
+                request_response_with_timeout(server.clone(), Message::Text(request.to_string()))
+                    .await
+                    .expect("no response from server within timeout")
+                    .expect("connection to server was closed")
+                    .expect("error in server response");
+
+            let expected = json!({
+                "jsonrpc": "2.0",
+                "id": expected_id,
+                "error": {"code": -32600, "message": "Invalid Request"}
+            });
+            assert!(json_response_validator(&response, &expected));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_notification_is_not_answered() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+        })))
+        .await;
+
+        let response = request_response_with_timeout(
+            server,
+            Message::Text(
+                json!({"jsonrpc": "2.0", "method": "org.rdk.System.1.getFriendlyName"}).to_string(),
+            ),
+        )
+        .await;
+        assert!(response.is_err(), "notification was answered {response:?}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_required_bearer_token() {
+        let config = MockConfig {
+            required_bearer_token: Some("secret".to_owned()),
+            ..Default::default()
+        };
+        let server =
+            MockWebSocketServer::new(HashMap::new(), WsServerParameters::default(), config)
+                .await
+                .expect("Unable to start server")
+                .into_arc();
+        tokio::spawn(server.clone().start_server());
+
+        for (authorization, accepted) in [
+            (Some("Bearer secret"), true),
+            (None, false),
+            (Some("Bearer wrong"), false),
+        ] {
+            let mut request = format!("ws://0.0.0.0:{}", server.port())
+                .into_client_request()
+                .unwrap();
+            if let Some(authorization) = authorization {
+                request
+                    .headers_mut()
+                    .insert(http::header::AUTHORIZATION, authorization.parse().unwrap());
+            }
+            match tokio_tungstenite::connect_async(request).await {
+                Ok(_) => assert!(accepted),
+                Err(Error::Http(response)) => {
+                    assert!(!accepted);
+                    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+                }
+                Err(e) => panic!("unexpected error {e:?}"),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_query_match_mode() {
+        let required = HashMap::from([("token".to_owned(), "abc".to_owned())]);
+        for (mode, query, accepted) in [
+            (QueryMatchMode::Exact, "token=abc", true),
+            (QueryMatchMode::Exact, "token=abc&cb=123", false),
+            (QueryMatchMode::Subset, "token=abc&cb=123", true),
+            (QueryMatchMode::Subset, "cb=123", false),
+            (QueryMatchMode::Subset, "token=xyz&cb=123", false),
+        ] {
+            let config = MockConfig {
+                query_match_mode: mode,
+                ..Default::default()
+            };
+            let server = MockWebSocketServer::new(
+                HashMap::new(),
+                WsServerParameters::new()
+                    .query_params(required.clone())
+                    .clone(),
+                config,
+            )
+            .await
+            .expect("Unable to start server")
+            .into_arc();
+            tokio::spawn(server.clone().start_server());
+
+            let url = format!("ws://0.0.0.0:{}/?{}", server.port(), query);
+            match tokio_tungstenite::connect_async(url).await {
+                Ok(_) => assert!(accepted, "{mode:?} {query}"),
+                Err(Error::Http(response)) => {
+                    assert!(!accepted, "{mode:?} {query}");
+                    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+                }
+                Err(e) => panic!("unexpected error {e:?}"),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_emit_notification_to_subscriber() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.DisplaySettings.1.register": [{"result": 0}],
+            "org.rdk.DisplaySettings.1.unregister": [{"result": 0}]
+        })))
+        .await;
+        let (client, _) =
+            tokio_tungstenite::connect_async(format!("ws://0.0.0.0:{}", server.port()))
+                .await
+                .expect("Unable to connect to WS server");
+        let (mut send, mut receive) = client.split();
+
+        // nobody subscribed yet
+        let params = json!({"width": 3840, "height": 2160});
+        assert_eq!(
+            server
+                .emit_notification("org.rdk.DisplaySettings.1.resolutionChanged", &params, 0)
+                .await,
+            0
+        );
+
+        send.send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "org.rdk.DisplaySettings.1.register",
+                "params": {"event": "resolutionChanged", "id": "client.events"}
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Failed to send message");
+        let response = time::timeout(Duration::from_secs(1), receive.next())
+            .await
+            .expect("no response from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server response");
+        assert!(json_response_validator(
+            &response,
+            &json!({"jsonrpc": "2.0", "id": 1, "result": 0})
+        ));
+
+        assert_eq!(
+            server
+                .emit_notification("org.rdk.DisplaySettings.1.resolutionChanged", &params, 0)
+                .await,
+            1
+        );
+        let notification = time::timeout(Duration::from_secs(1), receive.next())
+            .await
+            .expect("no notification from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server notification");
+        assert!(json_response_validator(
+            &notification,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "client.events.resolutionChanged",
+                "params": params
+            })
+        ));
+
+        // the method casing is ignored
+        assert_eq!(
+            server
+                .emit_notification("org.rdk.displaysettings.1.resolutionchanged", &params, 0)
+                .await,
+            1
+        );
+        time::timeout(Duration::from_secs(1), receive.next())
+            .await
+            .expect("no notification from server within timeout");
+
+        send.send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "org.rdk.DisplaySettings.1.unregister",
+                "params": {"event": "ResolutionChanged", "id": "client.events"}
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Failed to send message");
+        time::timeout(Duration::from_secs(1), receive.next())
+            .await
+            .expect("no response from server within timeout");
+        assert!(server.subscriptions.lock().unwrap().is_empty());
+        assert_eq!(
+            server
+                .emit_notification("org.rdk.DisplaySettings.1.resolutionChanged", &params, 0)
+                .await,
+            0
+        );
+
+        // the subscriptions of a peer are dropped when it disconnects
+        send.send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "org.rdk.DisplaySettings.1.register",
+                "params": {"event": "resolutionChanged", "id": "client.events"}
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Failed to send message");
+        time::timeout(Duration::from_secs(1), receive.next())
+            .await
+            .expect("no response from server within timeout");
+        assert_eq!(server.subscriptions.lock().unwrap().len(), 1);
+        drop(send.reunite(receive).unwrap());
+        time::timeout(Duration::from_secs(1), async {
+            while !server.subscriptions.lock().unwrap().is_empty() {
+                time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("subscriptions of the disconnected peer were not removed");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_health_endpoint() {
+        let config = MockConfig {
+            enable_health: true,
+            ..Default::default()
+        };
+        let server = MockWebSocketServer::new(
+            get_mock_data(json!({
+                "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+            })),
+            WsServerParameters::default(),
+            config,
+        )
+        .await
+        .expect("Unable to start server")
+        .into_arc();
+        tokio::spawn(server.clone().start_server());
+
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", server.port()))
+            .await
+            .expect("Unable to connect to server");
+        stream
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        time::timeout(Duration::from_secs(1), stream.read_to_string(&mut response))
+            .await
+            .expect("no response from server within timeout")
+            .unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let stats: ServerStats = serde_json::from_str(body).unwrap();
+        assert_eq!(stats.port, server.port());
+        assert_eq!(stats.mocked_methods, 1);
+
+        // WebSocket connections are still accepted on the same port
+        let response = request_response_with_timeout(
+            server,
+            Message::Text(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"})
+                    .to_string(),
+            ),
+        )
+        .await
+        .expect("no response from server within timeout")
+        .expect("connection to server was closed")
+        .expect("error in server response");
+        assert!(json_response_validator(
+            &response,
+            &json!({"jsonrpc": "2.0", "id": 1, "result": "Living Room"})
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_weighted_responses() {
+        async fn picks(seed: u64) -> Vec<bool> {
+            let mock_data = get_mock_data(json!({
+                "org.rdk.System.1.getFriendlyName": [
+                    {"result": "Living Room", "weight": 3},
+                    {"error": {"code": -32010, "message": "Unavailable"}, "weight": 1}
+                ]
+            }));
+            let config = MockConfig {
+                rng_seed: Some(seed),
+                ..Default::default()
+            };
+            let server = MockWebSocketServer::new(mock_data, WsServerParameters::default(), config)
+                .await
+                .expect("Unable to create server");
+            let request: JsonRpcApiRequest = serde_json::from_value(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"}),
+            )
+            .unwrap();
+            (0..1000)
+                .map(|_| {
+                    server
+                        .responses_for_key_v2(&request)
+                        .unwrap()
+                        .error
+                        .is_none()
+                })
+                .collect()
+        }
+
+        let first = picks(42).await;
+        let successes = first.iter().filter(|success| **success).count();
+        assert!((700..800).contains(&successes), "{successes} successes");
+        // the same seed gives the same sequence
+        assert_eq!(picks(42).await, first);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_poisoned_rng_lock_is_recovered() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [
+                {"result": "Living Room", "weight": 1}
+            ]
+        })))
+        .await;
+        std::thread::scope(|s| {
+            let _ = s
+                .spawn(|| {
+                    let _guard = server.rng.lock().unwrap();
+                    panic!("poisoning the rng lock");
+                })
+                .join();
+        });
+        assert!(server.rng.is_poisoned());
+
+        let request: JsonRpcApiRequest = serde_json::from_value(
+            json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"}),
+        )
+        .unwrap();
+        let response = server.responses_for_key_v2(&request).unwrap();
+        assert_eq!(response.result, Some(json!("Living Room")));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unweighted_responses_keep_first_match() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [
+                {"params": {"verbose": true}, "result": "first"},
+                {"params": {"verbose": true}, "result": "second"}
+            ]
+        })))
+        .await;
+        let request: JsonRpcApiRequest = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "org.rdk.System.1.getFriendlyName",
+            "params": {"verbose": true}
+        }))
+        .unwrap();
+        for _ in 0..100 {
+            let response = server.responses_for_key_v2(&request).unwrap();
+            assert_eq!(response.result, Some(json!("first")));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_plugin_states() {
+        let config = MockConfig {
+            activate_all_plugins: false,
+            plugin_states: HashMap::from([
+                ("AVInput".to_owned(), "deactivated".to_owned()),
+                ("DeviceInfo".to_owned(), "activated".to_owned()),
+            ]),
+            ..Default::default()
+        };
+        let server =
+            MockWebSocketServer::new(HashMap::new(), WsServerParameters::default(), config)
+                .await
+                .expect("Unable to start server")
+                .into_arc();
+        tokio::spawn(server.clone().start_server());
+
+        for (callsign, state) in [("AVInput", "deactivated"), ("DeviceInfo", "activated")] {
+            let method = format!("Controller.1.status@{callsign}");
+            let response = request_response_with_timeout(
+                server.clone(),
+                Message::Text(json!({"jsonrpc": "2.0", "id": 1, "method": method}).to_string()),
+            )
+            .await
+            .expect("no response from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server response");
+
+            let expected = json!({"id": 1, "jsonrpc": "2.0", "result": [{"state": state}]});
+            assert!(json_response_validator(&response, &expected));
+        }
+
+        // callsigns which are not configured are looked up in the mocks
+        let response = request_response_with_timeout(
+            server,
+            Message::Text(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "Controller.1.status@HdmiCec"})
+                    .to_string(),
+            ),
+        )
+        .await
+        .expect("no response from server within timeout")
+        .expect("connection to server was closed")
+        .expect("error in server response");
+
+        let expected = json!({
+            "id": 1,
+            "jsonrpc": "2.0",
+            "error": {"code": -32601, "message": "Method not found"}
+        });
+        assert!(json_response_validator(&response, &expected));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rejected_handshake_keeps_server_up() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+        })))
+        .await;
+
+        match tokio_tungstenite::connect_async(format!("ws://0.0.0.0:{}/wrong", server.port()))
+            .await
+        {
+            Err(Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            }
+            Ok(_) => panic!("expected the handshake to be rejected"),
+            Err(e) => panic!("unexpected error {e:?}"),
+        }
+
+        let response = request_response_with_timeout(
+            server,
+            Message::Text(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"})
+                    .to_string(),
+            ),
+        )
+        .await
+        .expect("no response from server within timeout")
+        .expect("connection to server was closed")
+        .expect("error in server response");
+        assert!(json_response_validator(
+            &response,
+            &json!({"jsonrpc": "2.0", "id": 1, "result": "Living Room"})
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_malformed_handshake_keeps_server_up() {
+        use ripple_sdk::tokio::io::AsyncWriteExt;
+
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+        })))
+        .await;
+
+        for garbage in [
+            "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".as_bytes(),
+            "not http at all\r\n\r\n".as_bytes(),
+        ] {
+            let mut stream = TcpStream::connect(format!("0.0.0.0:{}", server.port()))
+                .await
+                .unwrap();
+            stream.write_all(garbage).await.unwrap();
+            let _ = stream.shutdown().await;
+        }
+
+        let response = request_response_with_timeout(
+            server,
+            Message::Text(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "org.rdk.System.1.getFriendlyName"})
+                    .to_string(),
+            ),
+        )
+        .await
+        .expect("no response from server within timeout")
+        .expect("connection to server was closed")
+        .expect("error in server response");
+        assert!(json_response_validator(
+            &response,
+            &json!({"jsonrpc": "2.0", "id": 1, "result": "Living Room"})
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_strict_mode_records_unexpected_requests() {
+        let config = MockConfig {
+            strict: true,
+            ..Default::default()
+        };
+        let server = MockWebSocketServer::new(
+            get_mock_data(json!({
+                "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+            })),
+            WsServerParameters::default(),
+            config,
+        )
+        .await
+        .expect("Unable to start server")
+        .into_arc();
+        tokio::spawn(server.clone().start_server());
+
+        for (id, method) in [
+            (1, "org.rdk.System.1.getFriendlyName"),
+            (2, "org.rdk.System.1.getMode"),
+        ] {
+            let _ = request_response_with_timeout(
+                server.clone(),
+                Message::Text(
+                    json!({"jsonrpc": "2.0", "id": id, "method": method, "params": {"a": 1}})
+                        .to_string(),
+                ),
+            )
+            .await
+            .expect("no response from server within timeout")
+            .expect("connection to server was closed")
+            .expect("error in server response");
+        }
+
+        assert_eq!(
+            server.take_unexpected_requests(),
+            vec![UnexpectedRequest {
+                method: "org.rdk.System.1.getMode".to_owned(),
+                params: Some(json!({"a": 1})),
+            }]
+        );
+        assert!(server.take_unexpected_requests().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_expired_responses_fall_through() {
+        let server = start_server(get_mock_data(json!({
+            "org.rdk.System.1.getFriendlyName": [
+                {"result": "transient", "ttl_ms": 200, "match_any_params": false},
+                {"result": "fallback"}
+            ],
+            "org.rdk.System.1.getMode": [{"result": "transient", "ttl_ms": 200}]
+        })))
+        .await;
+        let request = |method: &str| json!({"jsonrpc": "2.0", "id": 1, "method": method});
+
+        for (method, expected) in [
+            (
+                "org.rdk.System.1.getFriendlyName",
+                json!({"jsonrpc": "2.0", "id": 1, "result": "transient"}),
+            ),
+            (
+                "org.rdk.System.1.getMode",
+                json!({"jsonrpc": "2.0", "id": 1, "result": "transient"}),
+            ),
+        ] {
+            let response = server.find_responses(request(method)).await.unwrap();
+            assert_eq!(response[0].data, expected);
+        }
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        for (method, expected) in [
+            (
+                "org.rdk.System.1.getFriendlyName",
+                json!({"jsonrpc": "2.0", "id": 1, "result": "fallback"}),
+            ),
+            (
+                "org.rdk.System.1.getMode",
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": {"code": -32601, "message": "Method not found"}
+                }),
+            ),
+        ] {
+            let response = server.find_responses(request(method)).await.unwrap();
+            assert_eq!(response[0].data, expected);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_history() {
+        let config = MockConfig {
+            history_size: 2,
+            ..Default::default()
+        };
+        let server = MockWebSocketServer::new(
+            get_mock_data(json!({
+                "org.rdk.System.1.getFriendlyName": [{"result": "Living Room"}]
+            })),
+            WsServerParameters::default(),
+            config,
+        )
+        .await
+        .expect("Unable to start server")
+        .into_arc();
+
+        for (id, method) in [
+            (1, "org.rdk.System.1.getMode"),
+            (2, "org.rdk.System.1.getFriendlyName"),
+            (3, "org.rdk.System.1.getTimeZone"),
+        ] {
+            server
+                .find_responses(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": method,
+                    "params": {"id": id}
+                }))
+                .await;
+        }
+
+        let history = server.history();
+        assert_eq!(
+            history
+                .iter()
+                .map(|e| (e.method.as_str(), e.params.clone(), e.matched))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "org.rdk.System.1.getFriendlyName",
+                    Some(json!({"id": 2})),
+                    true
+                ),
+                (
+                    "org.rdk.System.1.getTimeZone",
+                    Some(json!({"id": 3})),
+                    false
+                ),
+            ]
+        );
+        assert!(history[0].timestamp <= history[1].timestamp);
+    }
 }
@@ -15,7 +15,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::{fs::File, io::BufReader, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf, sync::Arc};
 
 use ripple_sdk::{
     api::config::Config,
@@ -69,6 +69,33 @@ pub async fn boot_ws_server(
     Ok(ws_server)
 }
 
+/// Starts the additional named servers listed in the `mock_servers` config, a JSON object
+/// mapping each server name to its port e.g. `{"cloud": 9998}`. Named servers start without
+/// any mock data.
+pub async fn boot_named_ws_servers(
+    client: &ExtnClient,
+) -> Result<HashMap<String, Arc<MockWebSocketServer>>, MockDeviceError> {
+    let mut servers = HashMap::new();
+    let Some(named) = client.get_config("mock_servers") else {
+        return Ok(servers);
+    };
+    let named: HashMap<String, u16> =
+        serde_json::from_str(&named).map_err(|_| BootFailedError::BadMockServersConfig)?;
+    for (name, port) in named {
+        debug!("Booting named mock server {name} on port {port}");
+        let mut server_config = WsServerParameters::new();
+        server_config.port(port);
+        let server = MockWebSocketServer::new(MockData::new(), server_config, load_config(client))
+            .await
+            .map_err(BootFailedError::ServerStartFailed)?
+            .into_arc();
+        tokio::spawn(server.clone().start_server());
+        servers.insert(name, server);
+    }
+
+    Ok(servers)
+}
+
 async fn platform_gateway_url(client: &mut ExtnClient) -> Result<Url, MockDeviceError> {
     debug!("sending request for config.platform_parameters");
     if let Ok(response) = client.request(Config::PlatformParameters).await {
@@ -143,6 +170,32 @@ pub fn load_config(client: &ExtnClient) -> MockConfig {
     if let Some(c) = client.get_config("activate_all_plugins") {
         config.activate_all_plugins = c.parse::<bool>().unwrap_or(false);
     }
+    config.required_bearer_token = client.get_config("required_bearer_token");
+    if let Some(c) = client.get_config("strict") {
+        config.strict = c.parse::<bool>().unwrap_or(false);
+    }
+    if let Some(size) = client
+        .get_config("history_size")
+        .and_then(|c| c.parse().ok())
+    {
+        config.history_size = size;
+    }
+    if let Some(mode) = client
+        .get_config("query_match_mode")
+        .and_then(|c| serde_json::from_value(Value::String(c)).ok())
+    {
+        config.query_match_mode = mode;
+    }
+    if let Some(states) = client
+        .get_config("plugin_states")
+        .and_then(|c| serde_json::from_str(&c).ok())
+    {
+        config.plugin_states = states;
+    }
+    if let Some(c) = client.get_config("enable_health") {
+        config.enable_health = c.parse::<bool>().unwrap_or(false);
+    }
+    config.rng_seed = client.get_config("rng_seed").and_then(|c| c.parse().ok());
     config
 }
 
@@ -169,7 +222,8 @@ pub async fn load_mock_data_v2(client: ExtnClient) -> Result<MockData, MockDevic
 
 pub fn is_value_jsonrpc(value: &Value) -> bool {
     value.as_object().map_or(false, |req| {
-        req.contains_key("jsonrpc") && req.contains_key("id") && req.contains_key("method")
+        req.get("jsonrpc").and_then(Value::as_str) == Some("2.0")
+            && req.get("method").map_or(false, Value::is_string)
     })
 }
 
@@ -186,8 +240,33 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_is_value_jsonrpc_notification() {
+        assert!(is_value_jsonrpc(
+            &json!({"jsonrpc": "2.0", "method": "someEvent", "params": {}})
+        ));
+    }
+
     #[test]
     fn test_is_value_jsonrpc_false() {
         assert!(!is_value_jsonrpc(&json!({"key": "value"})));
     }
+
+    #[test]
+    fn test_is_value_jsonrpc_missing_method() {
+        assert!(!is_value_jsonrpc(&json!({"jsonrpc": "2.0", "id": 1})));
+        assert!(!is_value_jsonrpc(
+            &json!({"jsonrpc": "2.0", "id": 1, "method": 1})
+        ));
+    }
+
+    #[test]
+    fn test_is_value_jsonrpc_wrong_version() {
+        assert!(!is_value_jsonrpc(
+            &json!({"jsonrpc": "1.0", "id": 1, "method": "someAction"})
+        ));
+        assert!(!is_value_jsonrpc(
+            &json!({"jsonrpc": 2.0, "id": 1, "method": "someAction"})
+        ));
+    }
 }